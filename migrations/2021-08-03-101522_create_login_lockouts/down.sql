@@ -0,0 +1,8 @@
+DROP TABLE rate_limit_buckets;
+
+DROP TABLE lockout_events;
+
+ALTER TABLE users
+DROP COLUMN failed_login_count,
+DROP COLUMN lockout_count,
+DROP COLUMN locked_until;
@@ -0,0 +1,31 @@
+ALTER TABLE users
+ADD COLUMN failed_login_count INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN lockout_count INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN locked_until TIMESTAMP;
+
+CREATE TABLE lockout_events (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    client_ip VARCHAR,
+    event_time TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL,
+    cleared_by INTEGER,
+    cleared_time TIMESTAMP
+);
+
+ALTER TABLE lockout_events
+ADD CONSTRAINT match_lockout_user_id
+FOREIGN KEY (user_id)
+REFERENCES users (id);
+
+ALTER TABLE lockout_events
+ADD CONSTRAINT match_lockout_cleared_by
+FOREIGN KEY (cleared_by)
+REFERENCES users (id);
+
+CREATE TABLE rate_limit_buckets (
+    bucket_key VARCHAR NOT NULL,
+    window_start TIMESTAMP NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (bucket_key, window_start)
+);
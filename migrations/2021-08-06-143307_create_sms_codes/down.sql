@@ -0,0 +1,7 @@
+DROP TABLE sms_codes;
+
+ALTER TABLE users
+DROP CONSTRAINT unique_phone_number;
+
+ALTER TABLE users
+ALTER COLUMN phone_number TYPE INTEGER USING NULL;
@@ -0,0 +1,18 @@
+ALTER TABLE users
+ALTER COLUMN phone_number TYPE VARCHAR USING phone_number::VARCHAR;
+
+ALTER TABLE users
+ADD CONSTRAINT unique_phone_number
+UNIQUE (phone_number);
+
+CREATE TABLE sms_codes (
+    id SERIAL PRIMARY KEY,
+    phone_number VARCHAR NOT NULL,
+    code_hashed VARCHAR NOT NULL,
+    created_time TIMESTAMP NOT NULL,
+    expiration_time TIMESTAMP NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    consumed_time TIMESTAMP
+);
+
+CREATE INDEX sms_codes_phone_number_index ON sms_codes (phone_number);
@@ -0,0 +1,32 @@
+ALTER TABLE users
+ADD COLUMN totp_secret VARCHAR,
+ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN totp_last_used_step BIGINT;
+
+CREATE TABLE totp_recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    code_hashed VARCHAR NOT NULL,
+    created_time TIMESTAMP NOT NULL,
+    used_time TIMESTAMP
+);
+
+ALTER TABLE totp_recovery_codes
+ADD CONSTRAINT match_recovery_code_user_id
+FOREIGN KEY (user_id)
+REFERENCES users (id);
+
+CREATE TABLE totp_policies (
+    user_role ROLE PRIMARY KEY,
+    totp_required BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_by INTEGER,
+    updated_time TIMESTAMP
+);
+
+ALTER TABLE totp_policies
+ADD CONSTRAINT match_totp_policy_updated_by
+FOREIGN KEY (updated_by)
+REFERENCES users (id);
+
+INSERT INTO totp_policies (user_role, totp_required)
+VALUES ('User', FALSE), ('Staff', FALSE), ('Admin', FALSE);
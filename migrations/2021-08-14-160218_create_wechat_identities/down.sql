@@ -0,0 +1 @@
+DROP TABLE wechat_identities;
@@ -0,0 +1,16 @@
+CREATE TABLE wechat_identities (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    appid VARCHAR NOT NULL,
+    openid VARCHAR NOT NULL,
+    unionid VARCHAR,
+    created_time TIMESTAMP NOT NULL,
+    UNIQUE (appid, openid)
+);
+
+CREATE INDEX wechat_identities_unionid_index ON wechat_identities (unionid);
+
+ALTER TABLE wechat_identities
+ADD CONSTRAINT match_wechat_identity_user_id
+FOREIGN KEY (user_id)
+REFERENCES users (id);
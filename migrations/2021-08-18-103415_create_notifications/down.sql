@@ -0,0 +1,5 @@
+DROP TABLE notifications;
+
+DROP TYPE NOTIFICATION_STATUS;
+
+DROP TYPE NOTIFICATION_CHANNEL;
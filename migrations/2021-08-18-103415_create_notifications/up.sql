@@ -0,0 +1,32 @@
+CREATE TYPE NOTIFICATION_CHANNEL AS ENUM ('Wechat');
+
+CREATE TYPE NOTIFICATION_STATUS AS ENUM ('Pending', 'Sent', 'Failed');
+
+CREATE TABLE notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    channel NOTIFICATION_CHANNEL NOT NULL,
+    recipient VARCHAR NOT NULL,
+    event_stage STAGE NOT NULL,
+    payload TEXT NOT NULL,
+    status NOTIFICATION_STATUS NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_time TIMESTAMP NOT NULL,
+    next_attempt_time TIMESTAMP NOT NULL,
+    sent_time TIMESTAMP
+);
+
+ALTER TABLE notifications
+ADD CONSTRAINT match_notification_user_id
+FOREIGN KEY (user_id)
+REFERENCES users (id);
+
+ALTER TABLE notifications
+ADD CONSTRAINT match_notification_product_id
+FOREIGN KEY (product_id)
+REFERENCES products (id);
+
+CREATE INDEX notifications_pending_index ON notifications (next_attempt_time)
+WHERE status = 'Pending';
@@ -0,0 +1,12 @@
+DROP TABLE notification_preferences;
+
+DELETE FROM notifications WHERE channel <> 'Wechat';
+
+ALTER TYPE NOTIFICATION_CHANNEL RENAME TO NOTIFICATION_CHANNEL_OLD;
+
+CREATE TYPE NOTIFICATION_CHANNEL AS ENUM ('Wechat');
+
+ALTER TABLE notifications
+ALTER COLUMN channel TYPE NOTIFICATION_CHANNEL USING channel::TEXT::NOTIFICATION_CHANNEL;
+
+DROP TYPE NOTIFICATION_CHANNEL_OLD;
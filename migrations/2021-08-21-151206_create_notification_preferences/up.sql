@@ -0,0 +1,19 @@
+ALTER TYPE NOTIFICATION_CHANNEL ADD VALUE 'Email';
+ALTER TYPE NOTIFICATION_CHANNEL ADD VALUE 'Sms';
+ALTER TYPE NOTIFICATION_CHANNEL ADD VALUE 'Webhook';
+
+CREATE TABLE notification_preferences (
+    user_id INTEGER PRIMARY KEY,
+    wechat_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    email_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    email VARCHAR,
+    sms_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    webhook_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    webhook_url VARCHAR,
+    updated_time TIMESTAMP NOT NULL
+);
+
+ALTER TABLE notification_preferences
+ADD CONSTRAINT match_notification_preference_user_id
+FOREIGN KEY (user_id)
+REFERENCES users (id);
@@ -0,0 +1,42 @@
+CREATE TABLE webhook_subscriptions (
+    id SERIAL PRIMARY KEY,
+    partner_name VARCHAR NOT NULL,
+    url VARCHAR NOT NULL,
+    secret VARCHAR NOT NULL,
+    event_types TEXT[] NOT NULL,
+    barcode_prefix VARCHAR,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INTEGER NOT NULL,
+    created_time TIMESTAMP NOT NULL,
+    updated_time TIMESTAMP NOT NULL
+);
+
+ALTER TABLE webhook_subscriptions
+ADD CONSTRAINT match_webhook_subscription_created_by
+FOREIGN KEY (created_by)
+REFERENCES users (id);
+
+CREATE TABLE webhook_deliveries (
+    id SERIAL PRIMARY KEY,
+    subscription_id INTEGER NOT NULL,
+    event_id uuid NOT NULL,
+    event_type VARCHAR NOT NULL,
+    payload TEXT NOT NULL,
+    status NOTIFICATION_STATUS NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    response_code INTEGER,
+    response_body TEXT,
+    last_error TEXT,
+    created_time TIMESTAMP NOT NULL,
+    next_attempt_time TIMESTAMP NOT NULL,
+    delivered_time TIMESTAMP
+);
+
+ALTER TABLE webhook_deliveries
+ADD CONSTRAINT match_webhook_delivery_subscription_id
+FOREIGN KEY (subscription_id)
+REFERENCES webhook_subscriptions (id)
+ON DELETE CASCADE;
+
+CREATE INDEX webhook_deliveries_pending_index ON webhook_deliveries (next_attempt_time)
+WHERE status = 'Pending';
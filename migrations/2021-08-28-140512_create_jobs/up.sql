@@ -0,0 +1,20 @@
+CREATE TYPE JOB_STATUS AS ENUM ('Pending', 'Running', 'Succeeded', 'Dead');
+
+CREATE TABLE jobs (
+    id SERIAL PRIMARY KEY,
+    job_type VARCHAR NOT NULL,
+    payload TEXT NOT NULL,
+    status JOB_STATUS NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    last_error TEXT,
+    -- Recurring jobs keep a single row, keyed by unique_key, and reschedule themselves
+    unique_key VARCHAR UNIQUE,
+    interval_seconds INTEGER,
+    created_time TIMESTAMP NOT NULL,
+    run_at TIMESTAMP NOT NULL,
+    finished_time TIMESTAMP
+);
+
+CREATE INDEX jobs_due_index ON jobs (run_at)
+WHERE status IN ('Pending', 'Running');
@@ -0,0 +1,4 @@
+ALTER TABLE notifications
+DROP COLUMN reminder_id;
+
+DROP TABLE product_reminders;
@@ -0,0 +1,21 @@
+CREATE TABLE product_reminders (
+    id SERIAL PRIMARY KEY,
+    product_id INTEGER NOT NULL,
+    stage STAGE NOT NULL,
+    created_time TIMESTAMP NOT NULL
+);
+
+ALTER TABLE product_reminders
+ADD CONSTRAINT match_product_reminder_product_id
+FOREIGN KEY (product_id)
+REFERENCES products (id);
+
+CREATE INDEX product_reminders_product_id_index ON product_reminders (product_id);
+
+ALTER TABLE notifications
+ADD COLUMN reminder_id INTEGER;
+
+ALTER TABLE notifications
+ADD CONSTRAINT match_notification_reminder_id
+FOREIGN KEY (reminder_id)
+REFERENCES product_reminders (id);
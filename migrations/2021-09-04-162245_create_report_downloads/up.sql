@@ -0,0 +1,28 @@
+CREATE TABLE report_downloads (
+    id SERIAL PRIMARY KEY,
+    report_id UUID NOT NULL,
+    -- Set for authenticated downloads
+    user_id INTEGER,
+    -- Set for downloads through a share link, to the user who created the link
+    shared_by INTEGER,
+    client_ip VARCHAR,
+    download_time TIMESTAMP NOT NULL
+);
+
+ALTER TABLE report_downloads
+ADD CONSTRAINT match_report_download_report_id
+FOREIGN KEY (report_id)
+REFERENCES reports (id)
+ON DELETE CASCADE;
+
+ALTER TABLE report_downloads
+ADD CONSTRAINT match_report_download_user_id
+FOREIGN KEY (user_id)
+REFERENCES users (id);
+
+ALTER TABLE report_downloads
+ADD CONSTRAINT match_report_download_shared_by
+FOREIGN KEY (shared_by)
+REFERENCES users (id);
+
+CREATE INDEX report_downloads_report_id_index ON report_downloads (report_id);
@@ -0,0 +1,4 @@
+ALTER TABLE reports
+DROP COLUMN sha256,
+DROP COLUMN file_size,
+DROP COLUMN page_count;
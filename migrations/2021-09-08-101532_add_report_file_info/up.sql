@@ -0,0 +1,4 @@
+ALTER TABLE reports
+ADD COLUMN sha256 VARCHAR,
+ADD COLUMN file_size BIGINT,
+ADD COLUMN page_count INTEGER;
@@ -0,0 +1,5 @@
+ALTER TABLE reports
+DROP COLUMN original_filename,
+DROP COLUMN result_summary,
+DROP COLUMN technician_comments,
+DROP COLUMN lab_accession_number;
@@ -0,0 +1,5 @@
+ALTER TABLE reports
+ADD COLUMN original_filename VARCHAR,
+ADD COLUMN result_summary TEXT,
+ADD COLUMN technician_comments TEXT,
+ADD COLUMN lab_accession_number VARCHAR;
@@ -0,0 +1,9 @@
+DROP INDEX reports_product_id_version_idx;
+
+ALTER TABLE reports
+DROP COLUMN product_id,
+DROP COLUMN version,
+DROP COLUMN amendment_reason,
+DROP COLUMN retracted_time,
+DROP COLUMN retracted_by,
+DROP COLUMN retraction_reason;
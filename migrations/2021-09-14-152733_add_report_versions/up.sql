@@ -0,0 +1,12 @@
+ALTER TABLE reports
+ADD COLUMN product_id INTEGER REFERENCES products(id),
+ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+ADD COLUMN amendment_reason TEXT,
+ADD COLUMN retracted_time TIMESTAMP,
+ADD COLUMN retracted_by INTEGER REFERENCES users(id),
+ADD COLUMN retraction_reason TEXT;
+
+UPDATE reports SET product_id = products.id
+FROM products WHERE products.report_id = reports.id;
+
+CREATE UNIQUE INDEX reports_product_id_version_idx ON reports (product_id, version);
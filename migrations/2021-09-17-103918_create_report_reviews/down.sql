@@ -0,0 +1,12 @@
+DROP INDEX reports_review_status_idx;
+
+ALTER TABLE reports
+DROP COLUMN review_status,
+DROP COLUMN reviewed_by,
+DROP COLUMN reviewed_time,
+DROP COLUMN review_comments;
+
+ALTER TABLE users
+DROP COLUMN report_reviewer;
+
+DROP TYPE REPORT_REVIEW_STATUS;
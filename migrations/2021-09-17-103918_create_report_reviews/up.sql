@@ -0,0 +1,13 @@
+CREATE TYPE REPORT_REVIEW_STATUS AS ENUM ('PendingReview','Approved','Rejected');
+
+ALTER TABLE users
+ADD COLUMN report_reviewer BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Reports released before the review workflow count as approved
+ALTER TABLE reports
+ADD COLUMN review_status REPORT_REVIEW_STATUS NOT NULL DEFAULT 'Approved',
+ADD COLUMN reviewed_by INTEGER REFERENCES users(id),
+ADD COLUMN reviewed_time TIMESTAMP,
+ADD COLUMN review_comments TEXT;
+
+CREATE INDEX reports_review_status_idx ON reports (review_status);
@@ -0,0 +1,5 @@
+ALTER TABLE reports
+DROP COLUMN template_id,
+DROP COLUMN result_items;
+
+DROP TABLE report_templates;
@@ -0,0 +1,29 @@
+CREATE TABLE report_templates (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE,
+    title VARCHAR NOT NULL,
+    header VARCHAR NOT NULL,
+    body TEXT NOT NULL,
+    footer VARCHAR NOT NULL,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_by INTEGER REFERENCES users(id),
+    updated_time TIMESTAMP NOT NULL
+);
+
+CREATE UNIQUE INDEX report_templates_default_index ON report_templates (is_default)
+WHERE is_default;
+
+INSERT INTO report_templates (name, title, header, body, footer, is_default, updated_time)
+VALUES (
+    'default',
+    '检测报告',
+    '医学检验实验室',
+    E'受检者：{{name}}\n身份证号：{{id_card_number}}\n出生日期：{{birth_date}}\n试剂盒条码：{{product_barcode}}\n采样时间：{{sample_time}}\n实验室编号：{{lab_accession_number}}\n\n# 检测结果\n{{results}}\n\n结论：{{result_summary}}\n备注：{{technician_comments}}\n\n报告时间：{{report_time}}',
+    '本报告仅对所检样本负责，扫描右上角二维码可验证报告真伪。',
+    TRUE,
+    NOW()
+);
+
+ALTER TABLE reports
+ADD COLUMN template_id INTEGER REFERENCES report_templates(id) ON DELETE SET NULL,
+ADD COLUMN result_items TEXT;
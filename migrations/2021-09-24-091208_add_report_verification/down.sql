@@ -0,0 +1,3 @@
+ALTER TABLE reports
+DROP COLUMN verification_code,
+DROP COLUMN result_category;
@@ -0,0 +1,9 @@
+ALTER TABLE reports
+ADD COLUMN verification_code VARCHAR,
+ADD COLUMN result_category VARCHAR;
+
+UPDATE reports SET verification_code = upper(substr(md5(random()::text || id::text), 1, 16));
+
+ALTER TABLE reports
+ALTER COLUMN verification_code SET NOT NULL,
+ADD CONSTRAINT reports_verification_code_key UNIQUE (verification_code);
@@ -0,0 +1,26 @@
+DROP INDEX reports_current_idx;
+DROP INDEX reports_product_id_test_type_id_version_idx;
+
+ALTER TABLE products
+ADD COLUMN report_id uuid;
+
+-- Only one report per product fits in products.report_id, the latest released one is kept
+UPDATE products SET report_id = (
+    SELECT id FROM reports
+    WHERE reports.product_id = products.id AND reports.is_current
+    ORDER BY reviewed_time DESC NULLS LAST, upload_time DESC
+    LIMIT 1
+);
+
+ALTER TABLE reports
+DROP COLUMN test_type_id,
+DROP COLUMN is_current;
+
+CREATE UNIQUE INDEX reports_product_id_version_idx ON reports (product_id, version);
+
+ALTER TABLE products
+DROP COLUMN kit_type_id;
+
+DROP TABLE kit_type_tests;
+DROP TABLE kit_types;
+DROP TABLE test_types;
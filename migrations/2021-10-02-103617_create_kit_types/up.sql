@@ -0,0 +1,36 @@
+CREATE TABLE test_types (
+    id SERIAL PRIMARY KEY,
+    code VARCHAR NOT NULL UNIQUE,
+    name VARCHAR NOT NULL
+);
+
+CREATE TABLE kit_types (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE
+);
+
+CREATE TABLE kit_type_tests (
+    kit_type_id INTEGER NOT NULL REFERENCES kit_types(id) ON DELETE CASCADE,
+    test_type_id INTEGER NOT NULL REFERENCES test_types(id),
+    PRIMARY KEY (kit_type_id, test_type_id)
+);
+
+ALTER TABLE products
+ADD COLUMN kit_type_id INTEGER REFERENCES kit_types(id);
+
+ALTER TABLE reports
+ADD COLUMN test_type_id INTEGER REFERENCES test_types(id),
+ADD COLUMN is_current BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE reports SET is_current = TRUE
+FROM products WHERE products.report_id = reports.id;
+
+ALTER TABLE products
+DROP COLUMN report_id;
+
+-- Versions are counted per test type, and each test type has at most one current report
+DROP INDEX reports_product_id_version_idx;
+CREATE UNIQUE INDEX reports_product_id_test_type_id_version_idx
+ON reports (product_id, COALESCE(test_type_id, 0), version);
+CREATE UNIQUE INDEX reports_current_idx
+ON reports (product_id, COALESCE(test_type_id, 0)) WHERE is_current;
@@ -0,0 +1,14 @@
+-- Only a report replacing an earlier released version counts as amended, rejected uploads
+-- still take up a version number
+ALTER TABLE reports
+ADD COLUMN amended BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE reports
+SET amended = EXISTS (
+    SELECT 1
+    FROM reports AS earlier
+    WHERE earlier.product_id = reports.product_id
+        AND COALESCE(earlier.test_type_id, 0) = COALESCE(reports.test_type_id, 0)
+        AND earlier.version < reports.version
+        AND earlier.review_status = 'Approved'
+);
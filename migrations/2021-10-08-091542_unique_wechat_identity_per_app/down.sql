@@ -0,0 +1,2 @@
+ALTER TABLE wechat_identities
+DROP CONSTRAINT wechat_identities_user_id_appid_key;
@@ -0,0 +1,9 @@
+-- An account may only hold one openid per WeChat app, the latest binding wins
+DELETE FROM wechat_identities
+USING wechat_identities AS newer
+WHERE wechat_identities.user_id = newer.user_id
+    AND wechat_identities.appid = newer.appid
+    AND (wechat_identities.created_time, wechat_identities.id) < (newer.created_time, newer.id);
+
+ALTER TABLE wechat_identities
+ADD CONSTRAINT wechat_identities_user_id_appid_key UNIQUE (user_id, appid);
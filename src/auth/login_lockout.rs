@@ -0,0 +1,106 @@
+use chrono::prelude::*;
+use chrono::{Duration, NaiveDateTime};
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use crate::auxiliary::read_config_or;
+use crate::database;
+use crate::models::{NewLockoutEvent, User};
+
+lazy_static! {
+    static ref LOGIN_LOCKOUT_THRESHOLD: i32 = read_config_or("LOGIN_LOCKOUT_THRESHOLD", 5);
+    static ref LOGIN_LOCKOUT_BASE_SECONDS: i64 = read_config_or("LOGIN_LOCKOUT_BASE_SECONDS", 60);
+    static ref LOGIN_LOCKOUT_MAX_SECONDS: i64 = read_config_or("LOGIN_LOCKOUT_MAX_SECONDS", 86400);
+}
+
+pub fn is_account_locked(user: &User) -> bool {
+    match user.locked_until {
+        Some(locked_until) => locked_until > Utc::now().naive_utc(),
+        None => false,
+    }
+}
+
+// Each lockout lasts twice as long as the previous one, until a successful login resets it
+fn lockout_duration(lockout_count: i32) -> Duration {
+    let multiplier = 2i64.saturating_pow(lockout_count.max(0) as u32);
+    Duration::seconds(
+        LOGIN_LOCKOUT_BASE_SECONDS
+            .saturating_mul(multiplier)
+            .min(*LOGIN_LOCKOUT_MAX_SECONDS),
+    )
+}
+
+/// Returns whether the account got locked by this failure.
+pub fn record_login_failure(
+    c: &PgConnection,
+    user_id: i32,
+    client_ip: Option<String>,
+) -> QueryResult<bool> {
+    c.transaction(|| {
+        let (failed_login_count, lockout_count): (i32, i32) =
+            diesel::update(database::users::table.find(user_id))
+                .set(
+                    database::users::failed_login_count.eq(database::users::failed_login_count + 1),
+                )
+                .returning((
+                    database::users::failed_login_count,
+                    database::users::lockout_count,
+                ))
+                .get_result(c)?;
+        if failed_login_count < *LOGIN_LOCKOUT_THRESHOLD {
+            return Ok(false);
+        }
+        let current_time = Utc::now().naive_utc();
+        let locked_until = current_time + lockout_duration(lockout_count);
+        diesel::update(database::users::table.find(user_id))
+            .set((
+                database::users::failed_login_count.eq(0),
+                database::users::lockout_count.eq(lockout_count + 1),
+                database::users::locked_until.eq(Some(locked_until)),
+            ))
+            .execute(c)?;
+        diesel::insert_into(database::lockout_events::table)
+            .values(NewLockoutEvent {
+                user_id,
+                client_ip,
+                event_time: current_time,
+                locked_until,
+            })
+            .execute(c)?;
+        warn!("用户{}登录失败次数过多，锁定至{}", user_id, locked_until);
+        Ok(true)
+    })
+}
+
+pub fn record_login_success(c: &PgConnection, user_id: i32) -> QueryResult<usize> {
+    diesel::update(database::users::table.find(user_id))
+        .set((
+            database::users::failed_login_count.eq(0),
+            database::users::lockout_count.eq(0),
+        ))
+        .execute(c)
+}
+
+pub fn clear_lockout(c: &PgConnection, user_id: i32, admin_id: i32) -> QueryResult<usize> {
+    c.transaction(|| {
+        let current_time = Utc::now().naive_utc();
+        diesel::update(
+            database::lockout_events::table
+                .filter(database::lockout_events::user_id.eq(user_id))
+                .filter(database::lockout_events::cleared_time.is_null()),
+        )
+        .set((
+            database::lockout_events::cleared_by.eq(Some(admin_id)),
+            database::lockout_events::cleared_time.eq(Some(current_time)),
+        ))
+        .execute(c)?;
+        diesel::update(database::users::table.find(user_id))
+            .set((
+                database::users::failed_login_count.eq(0),
+                database::users::lockout_count.eq(0),
+                database::users::locked_until.eq(None::<NaiveDateTime>),
+            ))
+            .execute(c)
+    })
+}
@@ -1,7 +1,19 @@
 mod admin_auth;
+mod login_lockout;
+mod report_reviewer_auth;
 mod staff_auth;
+mod totp;
+mod totp_pending_auth;
 mod user_auth;
+mod wechat_identity;
+mod wechat_signature;
 
 pub use admin_auth::*;
+pub use login_lockout::*;
+pub use report_reviewer_auth::*;
 pub use staff_auth::*;
+pub use totp::*;
+pub use totp_pending_auth::*;
 pub use user_auth::*;
+pub use wechat_identity::*;
+pub use wechat_signature::*;
@@ -0,0 +1,59 @@
+use diesel::prelude::*;
+
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{FromRequest, Request};
+
+use crate::auth::{check_totp_policy, decode_session_token};
+use crate::auxiliary::GenericError;
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::RoleEnum;
+
+/// Staff or admins that an admin has allowed to approve reports.
+pub struct ReportReviewerAuth {
+    pub user_id: i32,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ReportReviewerAuth {
+    type Error = GenericError;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        let claims = match decode_session_token(request) {
+            Ok(claims) => claims,
+            Err(failure) => return Outcome::Failure(failure),
+        };
+        if claims.user_role == RoleEnum::User {
+            return Outcome::Failure((Status::Forbidden, GenericError::PermissionDeniedError));
+        }
+        if let Err(failure) = check_totp_policy(request, &claims).await {
+            return Outcome::Failure(failure);
+        }
+        let db = match request.guard::<MainDatabaseConnection>().await {
+            Outcome::Success(db) => db,
+            _ => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    GenericError::ServerInternalError,
+                ))
+            }
+        };
+        let user_id = claims.user_id;
+        let report_reviewer = db
+            .run(move |c| {
+                database::users::table
+                    .find(user_id)
+                    .select(database::users::report_reviewer)
+                    .get_result::<bool>(c)
+            })
+            .await;
+        match report_reviewer {
+            Ok(true) => Outcome::Success(ReportReviewerAuth { user_id }),
+            Ok(false) => Outcome::Failure((Status::Forbidden, GenericError::PermissionDeniedError)),
+            Err(error) => Outcome::Failure((
+                Status::InternalServerError,
+                GenericError::DieselError(error),
+            )),
+        }
+    }
+}
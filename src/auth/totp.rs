@@ -0,0 +1,263 @@
+use chrono::prelude::*;
+
+use data_encoding::BASE32_NOPAD;
+
+use diesel::prelude::*;
+
+use hmac::{Hmac, Mac, NewMac};
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::Request;
+
+use sha1::Sha1;
+
+use uuid::Uuid;
+
+use crate::auth::TokenClaims;
+use crate::auxiliary::{read_config_or, GenericError};
+use crate::database::{self, MainDatabaseConnection};
+
+type HmacSha1 = Hmac<Sha1>;
+
+const TOTP_STEP_SECONDS: i64 = 30;
+const TOTP_DIGITS: usize = 6;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+lazy_static! {
+    static ref TOTP_ISSUER: String = read_config_or("TOTP_ISSUER", "MedKit".to_string());
+}
+
+pub fn gen_totp_secret() -> String {
+    let mut secret = Vec::with_capacity(20);
+    secret.extend_from_slice(Uuid::new_v4().as_bytes());
+    secret.extend_from_slice(&Uuid::new_v4().as_bytes()[..4]);
+    BASE32_NOPAD.encode(&secret)
+}
+
+pub fn gen_totp_uri(secret: &str, account_name: &str) -> String {
+    let issuer = utf8_percent_encode(&TOTP_ISSUER, NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        utf8_percent_encode(account_name, NON_ALPHANUMERIC),
+        secret,
+        issuer,
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS
+    )
+}
+
+// RFC 4226 HOTP with dynamic truncation
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC可接受任意长度的密钥");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(TOTP_DIGITS as u32)
+}
+
+/// Returns the matched time step, which must be stored to prevent the code from being replayed.
+pub fn verify_totp(secret: &str, code: &str, last_used_step: Option<i64>) -> Option<i64> {
+    verify_totp_at(secret, code, last_used_step, Utc::now().timestamp())
+}
+
+fn verify_totp_at(
+    secret: &str,
+    code: &str,
+    last_used_step: Option<i64>,
+    timestamp: i64,
+) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current_step = timestamp / TOTP_STEP_SECONDS;
+    // Tolerate one step of clock drift in either direction
+    (current_step - 1..=current_step + 1)
+        .filter(|step| !matches!(last_used_step, Some(last_step) if *step <= last_step))
+        .find(|step| hotp(&secret, *step as u64) == code)
+}
+
+pub fn gen_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let random_hex = Uuid::new_v4().to_simple().to_string();
+            format!("{}-{}", &random_hex[..5], &random_hex[5..10])
+        })
+        .collect()
+}
+
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.trim().to_lowercase()
+}
+
+pub async fn check_totp_policy(
+    request: &Request<'_>,
+    claims: &TokenClaims,
+) -> Result<(), (Status, GenericError)> {
+    if claims.totp_verified {
+        return Ok(());
+    }
+    let db = match request.guard::<MainDatabaseConnection>().await {
+        Outcome::Success(db) => db,
+        _ => {
+            return Err((
+                Status::InternalServerError,
+                GenericError::ServerInternalError,
+            ))
+        }
+    };
+    let user_role = claims.user_role;
+    let totp_required = db
+        .run(move |c| {
+            database::totp_policies::table
+                .find(user_role)
+                .select(database::totp_policies::totp_required)
+                .get_result::<bool>(c)
+                .optional()
+        })
+        .await
+        .map_err(|error| {
+            (
+                Status::InternalServerError,
+                GenericError::DieselError(error),
+            )
+        })?
+        .unwrap_or(false);
+    match totp_required {
+        true => Err((Status::Forbidden, GenericError::TotpRequiredError)),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The shared secret of both RFCs' SHA-1 test vectors
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    fn rfc_secret_base32() -> String {
+        BASE32_NOPAD.encode(RFC_SECRET)
+    }
+
+    fn totp_code(timestamp: i64) -> String {
+        format!(
+            "{:06}",
+            hotp(RFC_SECRET, (timestamp / TOTP_STEP_SECONDS) as u64)
+        )
+    }
+
+    #[test]
+    fn hotp_matches_rfc4226_vectors() {
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp(RFC_SECRET, counter as u64), *code);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc6238_vectors() {
+        // The RFC lists 8-digit codes, ours are their last 6 digits
+        let expected = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (timestamp, code) in expected.iter() {
+            let code = &code[2..];
+            assert_eq!(totp_code(*timestamp), code);
+            assert_eq!(
+                verify_totp_at(&rfc_secret_base32(), code, None, *timestamp),
+                Some(timestamp / TOTP_STEP_SECONDS)
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift() {
+        let secret = rfc_secret_base32();
+        // 1111111109 is the last second of step 37037036
+        let code = totp_code(1111111109);
+        let step = 1111111109 / TOTP_STEP_SECONDS;
+        let step_start = step * TOTP_STEP_SECONDS;
+        let accepted_from = step_start - TOTP_STEP_SECONDS;
+        let accepted_until = step_start + 2 * TOTP_STEP_SECONDS - 1;
+        assert_eq!(
+            verify_totp_at(&secret, &code, None, accepted_from),
+            Some(step)
+        );
+        assert_eq!(
+            verify_totp_at(&secret, &code, None, accepted_until),
+            Some(step)
+        );
+        assert_eq!(
+            verify_totp_at(&secret, &code, None, accepted_from - 1),
+            None
+        );
+        assert_eq!(
+            verify_totp_at(&secret, &code, None, accepted_until + 1),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_steps() {
+        let secret = rfc_secret_base32();
+        let code = totp_code(59);
+        assert_eq!(verify_totp_at(&secret, &code, Some(0), 59), Some(1));
+        assert_eq!(verify_totp_at(&secret, &code, Some(1), 59), None);
+        assert_eq!(verify_totp_at(&secret, &code, Some(2), 59), None);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let secret = rfc_secret_base32();
+        assert_eq!(verify_totp_at(&secret, " 287082 ", None, 59), Some(1));
+        assert_eq!(verify_totp_at(&secret, "28708", None, 59), None);
+        assert_eq!(verify_totp_at(&secret, "94287082", None, 59), None);
+        assert_eq!(verify_totp_at(&secret, "+28708", None, 59), None);
+        assert_eq!(verify_totp_at("not base32!", "287082", None, 59), None);
+    }
+
+    #[test]
+    fn generates_distinct_recovery_codes() {
+        let codes = gen_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        for code in codes.iter() {
+            assert_eq!(code.len(), 11);
+            assert_eq!(normalize_recovery_code(&code.to_uppercase()), *code);
+        }
+        let mut unique_codes = codes.clone();
+        unique_codes.sort();
+        unique_codes.dedup();
+        assert_eq!(unique_codes.len(), codes.len());
+        assert_eq!(normalize_recovery_code("  ABCDE-12345\n"), "abcde-12345");
+    }
+
+    #[test]
+    fn generates_usable_secrets() {
+        let secret = gen_totp_secret();
+        assert_eq!(BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(), 20);
+        let uri = gen_totp_uri(&secret, "staff@example");
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret)));
+        assert!(uri.contains("staff%40example"));
+    }
+}
@@ -0,0 +1,27 @@
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{FromRequest, Request};
+
+use crate::auth::decode_request_token;
+use crate::auxiliary::GenericError;
+
+pub struct TotpPendingAuth {
+    pub user_id: i32,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TotpPendingAuth {
+    type Error = GenericError;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        match decode_request_token(request) {
+            Ok(claims) => match claims.totp_pending {
+                true => Outcome::Success(TotpPendingAuth {
+                    user_id: claims.user_id,
+                }),
+                false => Outcome::Failure((Status::BadRequest, GenericError::TotpNotPendingError)),
+            },
+            Err(failure) => Outcome::Failure(failure),
+        }
+    }
+}
@@ -42,6 +42,12 @@ pub struct TokenClaims {
 
     pub user_id: i32,
     pub user_role: RoleEnum,
+
+    // Issued after the password step while the second factor is still outstanding
+    #[serde(default)]
+    pub totp_pending: bool,
+    #[serde(default)]
+    pub totp_verified: bool,
 }
 
 pub struct UserDigest {
@@ -49,60 +55,73 @@ pub struct UserDigest {
     pub user_role: RoleEnum,
 }
 
+pub fn decode_request_token(request: &Request<'_>) -> Result<TokenClaims, (Status, GenericError)> {
+    let token = match request.headers().get_one("Authorization") {
+        Some(token_string) => match token_string.starts_with("Bearer") {
+            true => token_string.get(7..).unwrap_or_default().to_string(),
+            false => return Err((Status::Unauthorized, GenericError::TokenError)),
+        },
+        None => match request.cookies().get("token") {
+            Some(token_cookie) => token_cookie.value().to_string(),
+            None => {
+                info!("No token was found.");
+                return Err((Status::Unauthorized, GenericError::AuthError));
+            }
+        },
+    };
+    decode::<TokenClaims>(&token, &USER_AUTH_DECODING_KEY, &USER_AUTH_VALIDATION)
+        .map(|decoded_claims| decoded_claims.claims)
+        .map_err(|error| {
+            info!("{:?}", error);
+            (Status::Unauthorized, GenericError::AuthError)
+        })
+}
+
+pub fn decode_session_token(request: &Request<'_>) -> Result<TokenClaims, (Status, GenericError)> {
+    let claims = decode_request_token(request)?;
+    match claims.totp_pending {
+        true => Err((Status::Unauthorized, GenericError::TotpPendingError)),
+        false => Ok(claims),
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for UserDigest {
     type Error = GenericError;
 
     async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
-        match request.headers().get_one("Authorization") {
-            Some(token_string) => match token_string.starts_with("Bearer") {
-                true => {
-                    match decode::<TokenClaims>(
-                        &token_string[7..],
-                        &USER_AUTH_DECODING_KEY,
-                        &USER_AUTH_VALIDATION,
-                    ) {
-                        Ok(decoded_claims) => Outcome::Success(UserDigest {
-                            user_id: decoded_claims.claims.user_id,
-                            user_role: decoded_claims.claims.user_role,
-                        }),
-                        Err(_) => Outcome::Failure((Status::Unauthorized, GenericError::AuthError)),
-                    }
-                }
-                false => Outcome::Failure((Status::Unauthorized, GenericError::TokenError)),
-            },
-            None => match request.cookies().get("token") {
-                Some(token_cookie) => {
-                    match decode::<TokenClaims>(
-                        token_cookie.value(),
-                        &USER_AUTH_DECODING_KEY,
-                        &USER_AUTH_VALIDATION,
-                    ) {
-                        Ok(decoded_claims) => Outcome::Success(UserDigest {
-                            user_id: decoded_claims.claims.user_id,
-                            user_role: decoded_claims.claims.user_role,
-                        }),
-                        Err(_) => Outcome::Failure((Status::Unauthorized, GenericError::AuthError)),
-                    }
-                }
-                None => Outcome::Failure((Status::Unauthorized, GenericError::AuthError)),
-            },
+        match decode_session_token(request) {
+            Ok(claims) => Outcome::Success(UserDigest {
+                user_id: claims.user_id,
+                user_role: claims.user_role,
+            }),
+            Err(failure) => Outcome::Failure(failure),
         }
     }
 }
 
-pub fn gen_token_cookie<'a>(user_id: i32, user_role: RoleEnum) -> Result<Cookie<'a>, GenericError> {
-    let expiration_datetime = Utc::now() + Duration::weeks(1);
+fn build_token_cookie<'a>(
+    user_id: i32,
+    user_role: RoleEnum,
+    totp_pending: bool,
+    totp_verified: bool,
+    valid_duration: Duration,
+) -> Result<Cookie<'a>, GenericError> {
+    let expiration_datetime = Utc::now() + valid_duration;
     let new_claims = TokenClaims {
         exp: expiration_datetime,
         user_id,
         user_role,
+        totp_pending,
+        totp_verified,
     };
     let token = encode(&USER_AUTH_HEADER, &new_claims, &USER_AUTH_ENCODING_KEY)
         .map_err(|_| GenericError::TokenError)?;
     //TODO: Cookie options
     let output_cookie = Cookie::build("token", token)
-        .expires(time::OffsetDateTime::now_utc() + time::Duration::weeks(1))
+        .expires(
+            time::OffsetDateTime::now_utc() + time::Duration::seconds(valid_duration.num_seconds()),
+        )
         // .http_only(true)
         //   .secure(true)
         //     .domain(env::var("COOKIE_DOMAIN").expect("未设置COOKIE_DOMAIN"))
@@ -111,6 +130,24 @@ pub fn gen_token_cookie<'a>(user_id: i32, user_role: RoleEnum) -> Result<Cookie<
     Ok(output_cookie)
 }
 
+pub fn gen_token_cookie<'a>(user_id: i32, user_role: RoleEnum) -> Result<Cookie<'a>, GenericError> {
+    build_token_cookie(user_id, user_role, false, false, Duration::weeks(1))
+}
+
+pub fn gen_totp_verified_token_cookie<'a>(
+    user_id: i32,
+    user_role: RoleEnum,
+) -> Result<Cookie<'a>, GenericError> {
+    build_token_cookie(user_id, user_role, false, true, Duration::weeks(1))
+}
+
+pub fn gen_totp_pending_token_cookie<'a>(
+    user_id: i32,
+    user_role: RoleEnum,
+) -> Result<Cookie<'a>, GenericError> {
+    build_token_cookie(user_id, user_role, true, false, Duration::minutes(5))
+}
+
 // Source: https://github.com/Keats/jsonwebtoken/blob/master/examples/custom_chrono.rs
 mod jwt_numeric_date {
     //! Custom serialization of DateTime<Utc> to conform with the JWT spec (RFC 7519 section 2, "Numeric Date")
@@ -0,0 +1,170 @@
+use chrono::prelude::*;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use crate::auxiliary::{GenericError, WECHAT_APPID};
+use crate::database;
+use crate::models::{NewUserData, NewWechatIdentity, RoleEnum, User, WechatIdentity};
+
+pub struct WechatIdentityClaim {
+    pub appid: String,
+    pub openid: String,
+    pub unionid: Option<String>,
+}
+
+// The unionid is shared by all our WeChat apps, so it takes precedence over the per-app openid.
+// Accounts created before wechat_identities existed only carry the Official Account openid.
+pub fn find_wechat_user(
+    c: &PgConnection,
+    claim: &WechatIdentityClaim,
+) -> QueryResult<Option<User>> {
+    if let Some(unionid) = &claim.unionid {
+        let user = database::users::table
+            .inner_join(database::wechat_identities::table)
+            .filter(database::wechat_identities::unionid.eq(unionid))
+            .select(database::users::all_columns)
+            .first::<User>(c)
+            .optional()?;
+        if user.is_some() {
+            return Ok(user);
+        }
+    }
+    let user = database::users::table
+        .inner_join(database::wechat_identities::table)
+        .filter(database::wechat_identities::appid.eq(&claim.appid))
+        .filter(database::wechat_identities::openid.eq(&claim.openid))
+        .select(database::users::all_columns)
+        .first::<User>(c)
+        .optional()?;
+    if user.is_some() || claim.appid != *WECHAT_APPID {
+        return Ok(user);
+    }
+    database::users::table
+        .filter(database::users::wechat_id.eq(Some(&claim.openid)))
+        .first::<User>(c)
+        .optional()
+}
+
+pub fn save_wechat_identity(
+    c: &PgConnection,
+    user_id: i32,
+    claim: &WechatIdentityClaim,
+) -> QueryResult<()> {
+    let existing_identity: Option<WechatIdentity> = database::wechat_identities::table
+        .filter(database::wechat_identities::appid.eq(&claim.appid))
+        .filter(database::wechat_identities::openid.eq(&claim.openid))
+        .first(c)
+        .optional()?;
+    match existing_identity {
+        Some(identity) => {
+            if claim.unionid.is_some() && identity.unionid != claim.unionid {
+                diesel::update(database::wechat_identities::table.find(identity.id))
+                    .set(database::wechat_identities::unionid.eq(&claim.unionid))
+                    .execute(c)?;
+            }
+        }
+        None => {
+            diesel::insert_into(database::wechat_identities::table)
+                .values(NewWechatIdentity {
+                    user_id,
+                    appid: claim.appid.clone(),
+                    openid: claim.openid.clone(),
+                    unionid: claim.unionid.clone(),
+                    created_time: Utc::now().naive_utc(),
+                })
+                .execute(c)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn resolve_wechat_user(
+    c: &PgConnection,
+    claim: &WechatIdentityClaim,
+) -> QueryResult<Option<User>> {
+    let user = find_wechat_user(c, claim)?;
+    if let Some(user) = &user {
+        save_wechat_identity(c, user.id, claim)?;
+    }
+    Ok(user)
+}
+
+pub fn resolve_or_create_wechat_user(
+    c: &PgConnection,
+    claim: &WechatIdentityClaim,
+    username: Option<String>,
+) -> QueryResult<User> {
+    c.transaction(|| match resolve_wechat_user(c, claim)? {
+        Some(user) => Ok(user),
+        None => {
+            let new_user = NewUserData {
+                username,
+                wechat_id: None,
+                user_role: RoleEnum::User,
+                password_hashed: None,
+                phone_number: None,
+                sign_up_time: Utc::now().naive_utc(),
+            };
+            let user: User = diesel::insert_into(database::users::table)
+                .values(new_user)
+                .get_result(c)?;
+            save_wechat_identity(c, user.id, claim)?;
+            Ok(user)
+        }
+    })
+}
+
+pub fn bind_wechat_identity(
+    c: &PgConnection,
+    user_id: i32,
+    claim: &WechatIdentityClaim,
+) -> Result<(), GenericError> {
+    c.transaction(|| {
+        if let Some(bound_user) = find_wechat_user(c, claim)? {
+            if bound_user.id != user_id {
+                return Err(GenericError::WechatAlreadyBoundError);
+            }
+        }
+        // Another WeChat account of the same app would leave notifications picking either openid
+        let bound_openid: Option<String> = database::wechat_identities::table
+            .filter(database::wechat_identities::user_id.eq(user_id))
+            .filter(database::wechat_identities::appid.eq(&claim.appid))
+            .select(database::wechat_identities::openid)
+            .first(c)
+            .optional()?;
+        let legacy_openid = match claim.appid == *WECHAT_APPID {
+            true => database::users::table
+                .find(user_id)
+                .select(database::users::wechat_id)
+                .get_result::<Option<String>>(c)?,
+            false => None,
+        };
+        if bound_openid
+            .iter()
+            .chain(legacy_openid.iter())
+            .any(|openid| *openid != claim.openid)
+        {
+            return Err(GenericError::WechatAppAlreadyBoundError);
+        }
+        save_wechat_identity(c, user_id, claim).map_err(GenericError::from)
+    })
+}
+
+pub fn unbind_wechat_identities(c: &PgConnection, user_id: i32) -> QueryResult<usize> {
+    c.transaction(|| {
+        let legacy_count = diesel::update(
+            database::users::table
+                .find(user_id)
+                .filter(database::users::wechat_id.is_not_null()),
+        )
+        .set(database::users::wechat_id.eq(None::<String>))
+        .execute(c)?;
+        let identity_count = diesel::delete(
+            database::wechat_identities::table
+                .filter(database::wechat_identities::user_id.eq(user_id)),
+        )
+        .execute(c)?;
+        Ok(legacy_count + identity_count)
+    })
+}
@@ -0,0 +1,62 @@
+use data_encoding::HEXLOWER;
+
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{FromRequest, Request};
+
+use sha1::{Digest, Sha1};
+
+use std::env;
+
+use crate::auxiliary::GenericError;
+
+lazy_static! {
+    static ref WECHAT_TOKEN: Option<String> = env::var("WECHAT_TOKEN").ok();
+}
+
+/// Requests signed by the WeChat server with the token configured on the Official Account.
+pub struct WechatSignature;
+
+fn calculate_signature(token: &str, timestamp: &str, nonce: &str) -> String {
+    let mut parts = [token, timestamp, nonce];
+    parts.sort_unstable();
+    HEXLOWER.encode(&Sha1::digest(parts.concat().as_bytes()))
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WechatSignature {
+    type Error = GenericError;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        let token = match &*WECHAT_TOKEN {
+            Some(token) => token,
+            None => {
+                error!("未设置WECHAT_TOKEN");
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    GenericError::ServerInternalError,
+                ));
+            }
+        };
+        let query_value = |name: &str| -> Option<&'r str> {
+            request
+                .query_value::<&'r str>(name)
+                .and_then(|value| value.ok())
+        };
+        match (
+            query_value("signature"),
+            query_value("timestamp"),
+            query_value("nonce"),
+        ) {
+            (Some(signature), Some(timestamp), Some(nonce))
+                if calculate_signature(token, timestamp, nonce) == signature =>
+            {
+                Outcome::Success(WechatSignature)
+            }
+            _ => {
+                warn!("微信签名校验失败：{:?}", request.client_ip());
+                Outcome::Failure((Status::Forbidden, GenericError::WechatSignatureError))
+            }
+        }
+    }
+}
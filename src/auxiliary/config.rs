@@ -0,0 +1,9 @@
+use std::env;
+use std::str::FromStr;
+
+pub fn read_config_or<T: FromStr>(config_key: &str, default: T) -> T {
+    env::var(config_key)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
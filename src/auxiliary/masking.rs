@@ -0,0 +1,27 @@
+/// Keeps the first 3 and last 4 characters, like 110***********1234.
+pub fn mask_id_card_number(id_card_number: &str) -> String {
+    let chars: Vec<char> = id_card_number.chars().collect();
+    if chars.len() < 8 {
+        return "*".repeat(chars.len());
+    }
+    chars
+        .iter()
+        .enumerate()
+        .map(|(index, c)| {
+            if index < 3 || index >= chars.len() - 4 {
+                *c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+/// Keeps only the first character, like 张**.
+pub fn mask_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .enumerate()
+        .map(|(index, c)| if index == 0 || c == ' ' { c } else { '*' })
+        .collect()
+}
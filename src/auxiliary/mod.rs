@@ -1,11 +1,41 @@
+mod config;
 mod cors;
+mod masking;
+mod password_policy;
+mod pdf_inspection;
+mod pdf_writer;
+mod phone_number;
 mod product_barcode;
+mod qrcode_svg;
+mod rate_limiter;
+mod report_link;
+mod report_pdf;
+mod report_signing;
+mod report_storage;
 mod responses;
+mod s3_storage;
+mod sms_sender;
 mod uuid_param;
 mod wechat_access_token;
+mod wechat_oauth;
 
+pub use config::*;
 pub use cors::*;
+pub use masking::*;
+pub use password_policy::*;
+pub use pdf_inspection::*;
+pub use pdf_writer::*;
+pub use phone_number::*;
 pub use product_barcode::*;
+pub use qrcode_svg::*;
+pub use rate_limiter::*;
+pub use report_link::*;
+pub use report_pdf::*;
+pub use report_signing::*;
+pub use report_storage::*;
 pub use responses::*;
+pub use s3_storage::*;
+pub use sms_sender::*;
 pub use uuid_param::*;
 pub use wechat_access_token::*;
+pub use wechat_oauth::*;
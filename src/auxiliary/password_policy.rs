@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+
+use crate::auxiliary::{read_config_or, GenericError};
+
+lazy_static! {
+    static ref PASSWORD_POLICY: PasswordPolicy = PasswordPolicy::from_env();
+    static ref COMMON_PASSWORDS: HashSet<&'static str> = include_str!("common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+}
+
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl PasswordPolicy {
+    fn from_env() -> Self {
+        Self {
+            min_length: read_config_or("PASSWORD_MIN_LENGTH", 8),
+            require_lowercase: read_config_or("PASSWORD_REQUIRE_LOWERCASE", false),
+            require_uppercase: read_config_or("PASSWORD_REQUIRE_UPPERCASE", false),
+            require_digit: read_config_or("PASSWORD_REQUIRE_DIGIT", true),
+            require_symbol: read_config_or("PASSWORD_REQUIRE_SYMBOL", false),
+        }
+    }
+}
+
+pub fn check_password_policy(password: &str) -> Result<(), GenericError> {
+    let policy = &*PASSWORD_POLICY;
+    if password.is_empty() {
+        return Err(GenericError::PasswordEmptyError);
+    }
+    if password.chars().count() < policy.min_length {
+        return Err(GenericError::PasswordTooShortError);
+    }
+    if policy.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+        return Err(GenericError::PasswordMissingLowercaseError);
+    }
+    if policy.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+        return Err(GenericError::PasswordMissingUppercaseError);
+    }
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err(GenericError::PasswordMissingDigitError);
+    }
+    if policy.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+        return Err(GenericError::PasswordMissingSymbolError);
+    }
+    if COMMON_PASSWORDS.contains(password.to_lowercase().as_str()) {
+        return Err(GenericError::PasswordTooCommonError);
+    }
+    Ok(())
+}
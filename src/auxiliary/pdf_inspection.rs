@@ -0,0 +1,421 @@
+use data_encoding::HEXLOWER;
+
+use flate2::read::ZlibDecoder;
+
+use rocket::data::{ByteUnit, ToByteUnit};
+
+use sha2::{Digest, Sha256};
+
+use std::io::Read;
+
+use crate::auxiliary::{read_config_or, GenericError};
+
+lazy_static! {
+    pub static ref REPORT_MAX_SIZE: ByteUnit = read_config_or("REPORT_MAX_SIZE", 20.mebibytes());
+}
+
+pub struct ReportFileInfo {
+    pub sha256: String,
+    pub file_size: i64,
+    pub page_count: i32,
+}
+
+struct PdfScan {
+    names: Vec<String>,
+    page_count: i32,
+    // Bytes that object streams may still inflate to, over the whole file
+    inflate_budget: u64,
+}
+
+impl PdfScan {
+    fn new(inflate_budget: u64) -> Self {
+        Self {
+            names: Vec::new(),
+            page_count: 0,
+            inflate_budget,
+        }
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| position + from)
+}
+
+// Names may spell characters as #xx, which is a known way of hiding /JavaScript
+fn decode_name(raw: &[u8]) -> String {
+    let mut decoded = Vec::with_capacity(raw.len());
+    let mut index = 0;
+    while index < raw.len() {
+        match (raw[index], raw.get(index + 1..index + 3)) {
+            (b'#', Some(hex)) => match u8::from_str_radix(&String::from_utf8_lossy(hex), 16) {
+                Ok(byte) => {
+                    decoded.push(byte);
+                    index += 3;
+                }
+                Err(_) => {
+                    decoded.push(b'#');
+                    index += 1;
+                }
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// A stream that fails to inflate may have been cut short by an `endstream` inside its data,
+// which readers going by /Length would still show, so it is never skipped
+fn inflate(data: &[u8], budget: &mut u64) -> Result<Vec<u8>, GenericError> {
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(data)
+        .take(*budget + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|_| GenericError::ReportPdfMalformedError)?;
+    *budget = budget
+        .checked_sub(inflated.len() as u64)
+        .ok_or(GenericError::ReportPdfMalformedError)?;
+    Ok(inflated)
+}
+
+/// Walks the PDF tokens, skipping strings, comments and stream data so that binary content
+/// can't be mistaken for names. Compressed object streams are unpacked and walked as well,
+/// streams inside them are not allowed by the spec and make the file count as malformed.
+fn scan(data: &[u8], result: &mut PdfScan, depth: u32) -> Result<(), GenericError> {
+    let mut object_names_start = result.names.len();
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        if is_whitespace(byte) {
+            index += 1;
+        } else if byte == b'%' {
+            while index < data.len() && data[index] != b'\n' && data[index] != b'\r' {
+                index += 1;
+            }
+        } else if byte == b'(' {
+            let mut depth = 0;
+            while index < data.len() {
+                match data[index] {
+                    b'\\' => index += 1,
+                    b'(' => depth += 1,
+                    b')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+                index += 1;
+            }
+            index += 1;
+        } else if data[index..].starts_with(b"<<") || data[index..].starts_with(b">>") {
+            index += 2;
+        } else if byte == b'<' {
+            index = find(data, b">", index).unwrap_or(data.len()) + 1;
+        } else if byte == b'/' {
+            let start = index + 1;
+            index = start;
+            while index < data.len() && !is_whitespace(data[index]) && !is_delimiter(data[index]) {
+                index += 1;
+            }
+            let name = decode_name(&data[start..index]);
+            if name == "Page"
+                && result.names.last().map(String::as_str) == Some("Type")
+                && result.names.len() > object_names_start
+            {
+                result.page_count += 1;
+            }
+            result.names.push(name);
+        } else if is_delimiter(byte) {
+            index += 1;
+        } else {
+            let start = index;
+            while index < data.len() && !is_whitespace(data[index]) && !is_delimiter(data[index]) {
+                index += 1;
+            }
+            match &data[start..index] {
+                b"obj" => object_names_start = result.names.len(),
+                b"stream" => {
+                    let mut stream_start = index;
+                    if data.get(stream_start) == Some(&b'\r') {
+                        stream_start += 1;
+                    }
+                    if data.get(stream_start) == Some(&b'\n') {
+                        stream_start += 1;
+                    }
+                    let stream_end = find(data, b"endstream", stream_start).unwrap_or(data.len());
+                    let object_names = &result.names[object_names_start..];
+                    let is_compressed_object_stream = object_names.iter().any(|n| n == "ObjStm")
+                        && object_names.iter().any(|n| n == "FlateDecode");
+                    if is_compressed_object_stream {
+                        if depth > 0 {
+                            return Err(GenericError::ReportPdfMalformedError);
+                        }
+                        let stream = &data[stream_start..stream_end];
+                        let inflated = inflate(stream, &mut result.inflate_budget)?;
+                        scan(&inflated, result, depth + 1)?;
+                    }
+                    index = stream_end + b"endstream".len();
+                    object_names_start = result.names.len();
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn has_valid_trailer(content: &[u8]) -> bool {
+    let tail_start = content.len().saturating_sub(1024);
+    let tail = &content[tail_start..];
+    let startxref = match find(tail, b"startxref", 0) {
+        Some(position) => position + b"startxref".len(),
+        None => return false,
+    };
+    if find(tail, b"%%EOF", startxref).is_none() {
+        return false;
+    }
+    let offset: String = tail[startxref..]
+        .iter()
+        .skip_while(|byte| is_whitespace(**byte))
+        .take_while(|byte| byte.is_ascii_digit())
+        .map(|byte| *byte as char)
+        .collect();
+    // The cross-reference section is either a table or a stream object
+    match offset.parse::<usize>() {
+        Ok(offset) if offset < content.len() => {
+            let section = &content[offset..content.len().min(offset + 32)];
+            section.starts_with(b"xref") || find(section, b"obj", 0).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Checks that the file is a well-formed PDF without encryption or scripts.
+pub fn inspect_report_file(content: &[u8]) -> Result<ReportFileInfo, GenericError> {
+    let header_end = content.len().min(1024);
+    if find(&content[..header_end], b"%PDF-", 0).is_none() {
+        return Err(GenericError::ReportNotPdfError);
+    }
+    if !has_valid_trailer(content) {
+        return Err(GenericError::ReportPdfMalformedError);
+    }
+    let mut result = PdfScan::new(REPORT_MAX_SIZE.as_u64());
+    scan(content, &mut result, 0)?;
+    if result.names.iter().any(|name| name == "Encrypt") {
+        return Err(GenericError::ReportPdfEncryptedError);
+    }
+    if result
+        .names
+        .iter()
+        .any(|name| name == "JavaScript" || name == "JS")
+    {
+        return Err(GenericError::ReportPdfScriptError);
+    }
+    if result.page_count == 0 {
+        return Err(GenericError::ReportPdfMalformedError);
+    }
+    Ok(ReportFileInfo {
+        sha256: HEXLOWER.encode(&Sha256::digest(content)),
+        file_size: content.len() as i64,
+        page_count: result.page_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    use std::io::Write;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn object_stream(content: &[u8]) -> Vec<u8> {
+        let compressed = compress(content);
+        let mut object = format!(
+            "<< /Type /ObjStm /N 1 /First 0 /Filter /FlateDecode /Length {} >>\nstream\n",
+            compressed.len()
+        )
+        .into_bytes();
+        object.extend_from_slice(&compressed);
+        object.extend_from_slice(b"\nendstream");
+        object
+    }
+
+    fn build_pdf(objects: &[Vec<u8>], trailer: &str) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.7\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n", objects.len() + 1).as_bytes());
+        pdf.extend_from_slice(b"0000000000 65535 f \n");
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R {} >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                trailer,
+                xref_offset
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+
+    fn pages(count: usize) -> Vec<Vec<u8>> {
+        let kids: Vec<String> = (0..count)
+            .map(|index| format!("{} 0 R", index + 3))
+            .collect();
+        let mut objects = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                count
+            )
+            .into_bytes(),
+        ];
+        for _ in 0..count {
+            objects.push(b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>".to_vec());
+        }
+        objects
+    }
+
+    #[test]
+    fn counts_pages() {
+        let info = inspect_report_file(&build_pdf(&pages(3), "")).ok().unwrap();
+        assert_eq!(info.page_count, 3);
+    }
+
+    #[test]
+    fn ignores_names_in_strings() {
+        let mut objects = pages(1);
+        objects.push(b"<< /Title (see /JavaScript \\) here) >>".to_vec());
+        assert!(inspect_report_file(&build_pdf(&objects, "")).is_ok());
+    }
+
+    #[test]
+    fn rejects_non_pdf() {
+        assert!(matches!(
+            inspect_report_file(b"hello world"),
+            Err(GenericError::ReportNotPdfError)
+        ));
+    }
+
+    #[test]
+    fn rejects_encrypted_file() {
+        let pdf = build_pdf(&pages(1), "/Encrypt << /Filter /Standard >>");
+        assert!(matches!(
+            inspect_report_file(&pdf),
+            Err(GenericError::ReportPdfEncryptedError)
+        ));
+    }
+
+    #[test]
+    fn rejects_escaped_javascript_name() {
+        let mut objects = pages(1);
+        objects.push(b"<< /S /J#61vaScript /JS#20 (app.alert(1)) >>".to_vec());
+        assert!(matches!(
+            inspect_report_file(&build_pdf(&objects, "")),
+            Err(GenericError::ReportPdfScriptError)
+        ));
+    }
+
+    #[test]
+    fn rejects_script_inside_object_stream() {
+        let mut objects = pages(1);
+        objects.push(object_stream(
+            b"5 0 << /S /JavaScript /JS (app.alert(1)) >>",
+        ));
+        assert!(matches!(
+            inspect_report_file(&build_pdf(&objects, "")),
+            Err(GenericError::ReportPdfScriptError)
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_trailer() {
+        let pdf = build_pdf(&pages(1), "");
+        let truncated = &pdf[..pdf.len() - 8];
+        assert!(matches!(
+            inspect_report_file(truncated),
+            Err(GenericError::ReportPdfMalformedError)
+        ));
+    }
+
+    #[test]
+    fn rejects_nested_object_streams() {
+        let mut inner = b"5 0 ".to_vec();
+        inner.extend_from_slice(&object_stream(b"6 0 << /Type /Page >>"));
+        assert!(scan(&inner, &mut PdfScan::new(1 << 20), 0).is_ok());
+        assert!(matches!(
+            scan(&inner, &mut PdfScan::new(1 << 20), 1),
+            Err(GenericError::ReportPdfMalformedError)
+        ));
+        let mut objects = pages(1);
+        objects.push(object_stream(&inner));
+        assert!(matches!(
+            inspect_report_file(&build_pdf(&objects, "")),
+            Err(GenericError::ReportPdfMalformedError)
+        ));
+    }
+
+    #[test]
+    fn rejects_corrupt_object_stream() {
+        let mut objects = pages(1);
+        let mut object = object_stream(b"5 0 << /S /JavaScript /JS (app.alert(1)) >>");
+        let data_start = find(&object, b"stream\n", 0).unwrap() + b"stream\n".len();
+        object.insert(data_start + 4, b'e');
+        objects.push(object);
+        assert!(matches!(
+            inspect_report_file(&build_pdf(&objects, "")),
+            Err(GenericError::ReportPdfMalformedError)
+        ));
+    }
+
+    #[test]
+    fn limits_total_inflated_size() {
+        let mut objects = pages(1);
+        for _ in 0..4 {
+            objects.push(object_stream(&[b' '; 1000]));
+        }
+        let pdf = build_pdf(&objects, "");
+        assert!(scan(&pdf, &mut PdfScan::new(4000), 0).is_ok());
+        assert!(matches!(
+            scan(&pdf, &mut PdfScan::new(3999), 0),
+            Err(GenericError::ReportPdfMalformedError)
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+pub const PDF_PAGE_WIDTH: f32 = 595.0;
+pub const PDF_PAGE_HEIGHT: f32 = 842.0;
+
+// STSong-Light is one of the standard CJK fonts every PDF reader provides, so nothing is embedded
+const CJK_FONT: &str = "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light \
+/Encoding /UniGB-UCS2-H /DescendantFonts [4 0 R] >>";
+const CJK_DESCENDANT_FONT: &str = "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light \
+/CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 2 >> /FontDescriptor 5 0 R \
+/DW 1000 /W [1 95 500] >>";
+const CJK_FONT_DESCRIPTOR: &str = "<< /Type /FontDescriptor /FontName /STSong-Light /Flags 6 \
+/FontBBox [-25 -254 1000 880] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 \
+/StemV 93 >>";
+
+/// Width of a line of text, half-width characters take half an em.
+pub fn pdf_text_width(text: &str, size: f32) -> f32 {
+    text.chars()
+        .map(|c| if c.is_ascii() { size / 2.0 } else { size })
+        .sum()
+}
+
+/// Drawing operations of a single page, coordinates start at the bottom left.
+#[derive(Default)]
+pub struct PdfPage {
+    content: String,
+}
+
+impl PdfPage {
+    pub fn text(&mut self, x: f32, y: f32, size: f32, text: &str) {
+        // UniGB-UCS2-H only covers the basic multilingual plane
+        let encoded: String = text
+            .chars()
+            .map(|c| match c as u32 {
+                code @ 0x20..=0xFFFF => format!("{:04X}", code),
+                _ => "003F".to_string(),
+            })
+            .collect();
+        let _ = writeln!(
+            self.content,
+            "BT /F1 {:.1} Tf {:.2} {:.2} Td <{}> Tj ET",
+            size, x, y, encoded
+        );
+    }
+
+    pub fn gray(&mut self, level: f32) {
+        let _ = writeln!(self.content, "{:.2} g {:.2} G", level, level);
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let _ = writeln!(
+            self.content,
+            "{:.2} {:.2} {:.2} {:.2} re f",
+            x, y, width, height
+        );
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let _ = writeln!(
+            self.content,
+            "0.5 w {:.2} {:.2} m {:.2} {:.2} l S",
+            x1, y1, x2, y2
+        );
+    }
+}
+
+/// A minimal A4 document writer, just enough for generated reports.
+#[derive(Default)]
+pub struct PdfDocument {
+    pages: Vec<PdfPage>,
+}
+
+impl PdfDocument {
+    pub fn add_page(&mut self, page: PdfPage) {
+        self.pages.push(page);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Objects 1 to 5 are the catalog, the page tree and the font, then each page takes two
+        let page_ids: Vec<usize> = (0..self.pages.len()).map(|index| 6 + index * 2).collect();
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            )
+            .into_bytes(),
+            CJK_FONT.as_bytes().to_vec(),
+            CJK_DESCENDANT_FONT.as_bytes().to_vec(),
+            CJK_FONT_DESCRIPTOR.as_bytes().to_vec(),
+        ];
+        for (page, page_id) in self.pages.iter().zip(page_ids) {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+/Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                    PDF_PAGE_WIDTH,
+                    PDF_PAGE_HEIGHT,
+                    page_id + 1
+                )
+                .into_bytes(),
+            );
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            let _ = encoder.write_all(page.content.as_bytes());
+            let compressed = encoder.finish().unwrap_or_default();
+            let mut stream = format!(
+                "<< /Length {} /Filter /FlateDecode >>\nstream\n",
+                compressed.len()
+            )
+            .into_bytes();
+            stream.extend_from_slice(&compressed);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut output = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(output.len());
+            output.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            output.extend_from_slice(object);
+            output.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = output.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            xref,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        );
+        output.extend_from_slice(xref.as_bytes());
+        output
+    }
+}
@@ -0,0 +1,24 @@
+use crate::auxiliary::GenericError;
+
+// Accepts mainland China mobile numbers, with or without the +86 country code
+pub fn normalize_phone_number(input: &str) -> Result<String, GenericError> {
+    let digits: String = input
+        .chars()
+        .filter(|c| !(c.is_whitespace() || *c == '-'))
+        .collect();
+    let digits = digits.strip_prefix('+').unwrap_or(&digits);
+    let digits = match digits.len() {
+        13 => digits.strip_prefix("86").unwrap_or(digits),
+        _ => digits,
+    };
+    let mut chars = digits.chars();
+    let is_valid = digits.len() == 11
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && chars.next() == Some('1')
+        && matches!(chars.next(), Some('3'..='9'));
+    if is_valid {
+        Ok(digits.to_string())
+    } else {
+        Err(GenericError::InvalidPhoneNumberError)
+    }
+}
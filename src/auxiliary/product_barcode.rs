@@ -10,6 +10,12 @@ use std::{
     fs::File,
     io::{prelude::*, BufReader},
 };
+
+lazy_static! {
+    pub static ref QRCODE_DOMAIN_ROOT: String =
+        env::var("QRCODE_ROOT_DOMAIN").expect("未设置QRCODE_ROOT_DOMAIN");
+}
+
 pub struct ProductBarcode<'a>(&'a str);
 
 impl<'a> FromParam<'a> for ProductBarcode<'a> {
@@ -0,0 +1,15 @@
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+use crate::auxiliary::GenericError;
+
+pub fn gen_qrcode_svg(content: &str) -> Result<String, GenericError> {
+    let qrcode = QrCode::new(content.as_bytes()).map_err(|error| {
+        error!("生成二维码时出错：{:?}", error);
+        GenericError::ServerInternalError
+    })?;
+    Ok(qrcode
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build())
+}
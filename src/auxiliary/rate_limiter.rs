@@ -0,0 +1,197 @@
+use chrono::prelude::*;
+use chrono::NaiveDateTime;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{FromRequest, Request};
+use rocket::tokio::sync::Mutex;
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::auxiliary::{read_config_or, GenericError};
+use crate::database::{self, MainDatabaseConnection};
+
+pub enum RateLimitBackend {
+    Memory,
+    Postgres,
+}
+
+pub struct RateLimiterState {
+    backend: RateLimitBackend,
+    max_hits: i32,
+    window_seconds: i64,
+    // Peers whose X-Real-IP header is believed
+    trusted_proxies: Vec<IpAddr>,
+    buckets: Mutex<HashMap<String, (i64, i32)>>,
+}
+
+impl RateLimiterState {
+    pub fn load() -> Self {
+        let backend = match read_config_or("RATE_LIMIT_BACKEND", "memory".to_string())
+            .to_lowercase()
+            .as_ref()
+        {
+            "postgres" => RateLimitBackend::Postgres,
+            _ => RateLimitBackend::Memory,
+        };
+        Self {
+            backend,
+            max_hits: read_config_or("RATE_LIMIT_MAX_REQUESTS", 10),
+            window_seconds: read_config_or("RATE_LIMIT_WINDOW_SECONDS", 60).max(1),
+            trusted_proxies: read_config_or("TRUSTED_PROXIES", "127.0.0.1,::1".to_string())
+                .split(',')
+                .filter_map(|ip| ip.trim().parse().ok())
+                .collect(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Anyone reaching Rocket directly could send a new X-Real-IP with every request, so the
+    /// header only counts when the connection comes from our own proxy.
+    fn client_ip(&self, request: &Request<'_>) -> Option<IpAddr> {
+        let remote_ip = request.remote().map(|remote| remote.ip());
+        match remote_ip {
+            Some(remote_ip) if self.trusted_proxies.contains(&remote_ip) => {
+                request.real_ip().or(Some(remote_ip))
+            }
+            _ => remote_ip,
+        }
+    }
+
+    async fn hit_in_memory(&self, bucket_key: String, window_start: i64) -> i32 {
+        let mut buckets = self.buckets.lock().await;
+        // Buckets of previous windows are useless, drop them before the map grows too large
+        if buckets.len() > 10000 {
+            buckets.retain(|_, (bucket_window, _)| *bucket_window == window_start);
+        }
+        let bucket = buckets.entry(bucket_key).or_insert((window_start, 0));
+        if bucket.0 != window_start {
+            *bucket = (window_start, 0);
+        }
+        bucket.1 += 1;
+        bucket.1
+    }
+
+    /// Counts one hit in the current window, `db` is only needed by the Postgres backend.
+    async fn hit(
+        &self,
+        db: Option<&MainDatabaseConnection>,
+        bucket_key: String,
+    ) -> Result<i32, GenericError> {
+        let current_timestamp = Utc::now().timestamp();
+        let window_start = current_timestamp - current_timestamp % self.window_seconds;
+        match self.backend {
+            RateLimitBackend::Memory => Ok(self.hit_in_memory(bucket_key, window_start).await),
+            RateLimitBackend::Postgres => {
+                let db = db.ok_or(GenericError::ServerInternalError)?;
+                let window_start = NaiveDateTime::from_timestamp(window_start, 0);
+                Ok(db
+                    .run(move |c| hit_in_database(c, bucket_key, window_start))
+                    .await?)
+            }
+        }
+    }
+}
+
+fn hit_in_database(
+    c: &PgConnection,
+    bucket_key: String,
+    window_start: NaiveDateTime,
+) -> QueryResult<i32> {
+    diesel::delete(
+        database::rate_limit_buckets::table
+            .filter(database::rate_limit_buckets::window_start.lt(window_start)),
+    )
+    .execute(c)?;
+    diesel::insert_into(database::rate_limit_buckets::table)
+        .values((
+            database::rate_limit_buckets::bucket_key.eq(bucket_key),
+            database::rate_limit_buckets::window_start.eq(window_start),
+            database::rate_limit_buckets::hits.eq(1),
+        ))
+        .on_conflict((
+            database::rate_limit_buckets::bucket_key,
+            database::rate_limit_buckets::window_start,
+        ))
+        .do_update()
+        .set(database::rate_limit_buckets::hits.eq(database::rate_limit_buckets::hits + 1))
+        .returning(database::rate_limit_buckets::hits)
+        .get_result(c)
+}
+
+/// Limits requests per client address, handlers that know which account is targeted should
+/// also call `check_identity` so guesses spread over many addresses are limited as well.
+pub struct RateLimit {
+    pub client_ip: Option<IpAddr>,
+    scope: String,
+}
+
+impl RateLimit {
+    pub async fn check_identity(
+        &self,
+        limiter: &RateLimiterState,
+        db: &MainDatabaseConnection,
+        identity: &str,
+    ) -> Result<(), GenericError> {
+        let bucket_key = format!("{}:identity:{}", self.scope, identity.to_lowercase());
+        if limiter.hit(Some(db), bucket_key).await? > limiter.max_hits {
+            info!("请求过于频繁：{}", identity);
+            return Err(GenericError::TooManyRequestsError);
+        }
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RateLimit {
+    type Error = GenericError;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        let limiter = match request.rocket().state::<RateLimiterState>() {
+            Some(limiter) => limiter,
+            None => {
+                error!("RateLimiterState未加载");
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    GenericError::ServerInternalError,
+                ));
+            }
+        };
+        let client_ip = limiter.client_ip(request);
+        let scope = request
+            .route()
+            .and_then(|route| route.name.as_deref())
+            .unwrap_or("default")
+            .to_string();
+        let bucket_key = match client_ip {
+            Some(ip) => format!("{}:{}", scope, ip),
+            None => format!("{}:unknown", scope),
+        };
+        let db = match limiter.backend {
+            RateLimitBackend::Memory => None,
+            RateLimitBackend::Postgres => match request.guard::<MainDatabaseConnection>().await {
+                Outcome::Success(db) => Some(db),
+                _ => {
+                    return Outcome::Failure((
+                        Status::InternalServerError,
+                        GenericError::ServerInternalError,
+                    ))
+                }
+            },
+        };
+        let hits = match limiter.hit(db.as_ref(), bucket_key).await {
+            Ok(hits) => hits,
+            Err(error) => return Outcome::Failure((Status::InternalServerError, error)),
+        };
+        if hits > limiter.max_hits {
+            info!("请求过于频繁：{:?}", client_ip);
+            Outcome::Failure((Status::TooManyRequests, GenericError::TooManyRequestsError))
+        } else {
+            Outcome::Success(RateLimit { client_ip, scope })
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::prelude::*;
+
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+
+use hmac::{Hmac, Mac, NewMac};
+
+use sha2::Sha256;
+
+use std::env;
+
+use uuid::Uuid;
+
+use crate::auxiliary::read_config_or;
+
+type HmacSha256 = Hmac<Sha256>;
+
+lazy_static! {
+    static ref REPORT_LINK_SECRET: String =
+        env::var("REPORT_LINK_SECRET").expect("未设置REPORT_LINK_SECRET");
+    pub static ref REPORT_LINK_BASE: String =
+        read_config_or("REPORT_LINK_BASE", "/api/report".to_string())
+            .trim_end_matches('/')
+            .to_string();
+    pub static ref REPORT_LINK_TTL_SECONDS: i64 = read_config_or("REPORT_LINK_TTL_SECONDS", 900);
+    pub static ref REPORT_LINK_MAX_TTL_SECONDS: i64 =
+        read_config_or("REPORT_LINK_MAX_TTL_SECONDS", 86400);
+    // Printed as a QR code on generated reports, so it should be an absolute URL
+    static ref REPORT_VERIFY_URL_BASE: String = read_config_or(
+        "REPORT_VERIFY_URL_BASE",
+        format!("{}/verify", *REPORT_LINK_BASE)
+    )
+    .trim_end_matches('/')
+    .to_string();
+}
+
+fn report_link_mac(report_id: Uuid, expires: i64, shared_by: i32) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(REPORT_LINK_SECRET.as_bytes())
+        .expect("HMAC可接受任意长度的密钥");
+    mac.update(format!("{}.{}.{}", report_id, expires, shared_by).as_bytes());
+    mac
+}
+
+pub fn report_download_path(report_id: Uuid) -> String {
+    format!("{}/download/{}", *REPORT_LINK_BASE, report_id)
+}
+
+/// Derived from the report id so it can be printed before the report is stored,
+/// but can't be guessed without the secret.
+pub fn report_verification_code(report_id: Uuid) -> String {
+    let mut mac = HmacSha256::new_from_slice(REPORT_LINK_SECRET.as_bytes())
+        .expect("HMAC可接受任意长度的密钥");
+    mac.update(format!("verify.{}", report_id).as_bytes());
+    BASE32_NOPAD.encode(&mac.finalize().into_bytes()[..10])
+}
+
+pub fn report_verification_url(verification_code: &str) -> String {
+    format!("{}/{}", *REPORT_VERIFY_URL_BASE, verification_code)
+}
+
+pub fn build_report_share_link(report_id: Uuid, expires: i64, shared_by: i32) -> String {
+    let signature = HEXLOWER.encode(
+        &report_link_mac(report_id, expires, shared_by)
+            .finalize()
+            .into_bytes(),
+    );
+    format!(
+        "{}/shared/{}?expires={}&by={}&signature={}",
+        *REPORT_LINK_BASE, report_id, expires, shared_by, signature
+    )
+}
+
+pub fn verify_report_link(report_id: Uuid, expires: i64, shared_by: i32, signature: &str) -> bool {
+    let signature = match HEXLOWER.decode(signature.as_bytes()) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    report_link_mac(report_id, expires, shared_by)
+        .verify(&signature)
+        .is_ok()
+}
+
+pub fn is_report_link_expired(expires: i64) -> bool {
+    Utc::now().timestamp() > expires
+}
@@ -0,0 +1,245 @@
+use qrcode::{Color, QrCode};
+
+use crate::auxiliary::{
+    pdf_text_width, GenericError, PdfDocument, PdfPage, PDF_PAGE_HEIGHT, PDF_PAGE_WIDTH,
+};
+use crate::models::{ReportResultItem, ReportTemplate};
+
+const MARGIN: f32 = 50.0;
+const QRCODE_SIZE: f32 = 80.0;
+const HEADER_SIZE: f32 = 10.0;
+const TITLE_SIZE: f32 = 20.0;
+const HEADING_SIZE: f32 = 14.0;
+const BODY_SIZE: f32 = 11.0;
+const FOOTER_SIZE: f32 = 8.0;
+const RESULT_COLUMNS: [f32; 4] = [0.0, 190.0, 300.0, 370.0];
+const RESULT_COLUMN_GAP: f32 = 8.0;
+
+/// Values for the `{{...}}` placeholders of a report template.
+pub struct ReportRenderData {
+    pub name: String,
+    pub id_card_number: String,
+    pub birth_date: String,
+    pub product_barcode: String,
+    pub sample_time: String,
+    pub report_time: String,
+    pub result_summary: String,
+    pub technician_comments: String,
+    pub lab_accession_number: String,
+    pub results: Vec<ReportResultItem>,
+    pub verification_url: String,
+}
+
+impl ReportRenderData {
+    fn placeholders(&self) -> [(&'static str, &str); 10] {
+        [
+            ("{{name}}", &self.name),
+            ("{{id_card_number}}", &self.id_card_number),
+            ("{{birth_date}}", &self.birth_date),
+            ("{{product_barcode}}", &self.product_barcode),
+            ("{{sample_time}}", &self.sample_time),
+            ("{{report_time}}", &self.report_time),
+            ("{{result_summary}}", &self.result_summary),
+            ("{{technician_comments}}", &self.technician_comments),
+            ("{{lab_accession_number}}", &self.lab_accession_number),
+            ("{{verification_url}}", &self.verification_url),
+        ]
+    }
+}
+
+fn wrap_text(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for c in text.chars() {
+        let current = lines.last_mut().expect("至少有一行");
+        if !current.is_empty() && pdf_text_width(&format!("{}{}", current, c), size) > max_width {
+            lines.push(c.to_string());
+        } else {
+            current.push(c);
+        }
+    }
+    lines
+}
+
+enum RenderLine {
+    Text(String, f32),
+    ResultRow([String; 4], bool),
+    Blank,
+}
+
+/// Wraps every cell to its own column. A row taller than one line becomes several
+/// `ResultRow`s and only the last one of a header row is underlined.
+fn layout_result_row(cells: [String; 4], is_header: bool) -> Vec<RenderLine> {
+    let text_width = PDF_PAGE_WIDTH - MARGIN * 2.0;
+    let wrapped: Vec<Vec<String>> = cells
+        .iter()
+        .enumerate()
+        .map(|(index, cell)| {
+            let column_end = RESULT_COLUMNS.get(index + 1).copied().unwrap_or(text_width);
+            let column_width = column_end - RESULT_COLUMNS[index] - RESULT_COLUMN_GAP;
+            wrap_text(cell, BODY_SIZE, column_width)
+        })
+        .collect();
+    let row_count = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+    (0..row_count)
+        .map(|row| {
+            let cells =
+                [0, 1, 2, 3].map(|index| wrapped[index].get(row).cloned().unwrap_or_default());
+            RenderLine::ResultRow(cells, is_header && row + 1 == row_count)
+        })
+        .collect()
+}
+
+fn layout_body(template: &ReportTemplate, data: &ReportRenderData) -> Vec<RenderLine> {
+    let text_width = PDF_PAGE_WIDTH - MARGIN * 2.0;
+    let mut lines = Vec::new();
+    for template_line in template.body.lines() {
+        let template_line = template_line.trim_end();
+        if template_line.trim() == "{{results}}" {
+            lines.extend(layout_result_row(
+                [
+                    "检测项目".to_string(),
+                    "结果".to_string(),
+                    "单位".to_string(),
+                    "参考范围".to_string(),
+                ],
+                true,
+            ));
+            for result in &data.results {
+                lines.extend(layout_result_row(
+                    [
+                        result.item.to_owned(),
+                        result.result.to_owned(),
+                        result.unit.to_owned().unwrap_or_default(),
+                        result.reference_range.to_owned().unwrap_or_default(),
+                    ],
+                    false,
+                ));
+            }
+            continue;
+        }
+        if template_line.is_empty() {
+            lines.push(RenderLine::Blank);
+            continue;
+        }
+        let (text, size) = match template_line.strip_prefix("# ") {
+            Some(heading) => (heading, HEADING_SIZE),
+            None => (template_line, BODY_SIZE),
+        };
+        let text = data
+            .placeholders()
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, value)| {
+                text.replace(placeholder, value)
+            });
+        for wrapped in wrap_text(&text, size, text_width) {
+            lines.push(RenderLine::Text(wrapped, size));
+        }
+    }
+    lines
+}
+
+fn draw_qrcode(page: &mut PdfPage, content: &str, x: f32, y: f32) -> Result<(), GenericError> {
+    let qrcode = QrCode::new(content.as_bytes()).map_err(|error| {
+        error!("生成二维码时出错：{:?}", error);
+        GenericError::ServerInternalError
+    })?;
+    let width = qrcode.width();
+    let module_size = QRCODE_SIZE / width as f32;
+    for (index, color) in qrcode.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let (column, row) = (index % width, index / width);
+            page.rect(
+                x + column as f32 * module_size,
+                y + QRCODE_SIZE - (row + 1) as f32 * module_size,
+                module_size,
+                module_size,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn start_page(template: &ReportTemplate) -> PdfPage {
+    let mut page = PdfPage::default();
+    page.gray(0.4);
+    page.text(
+        MARGIN,
+        PDF_PAGE_HEIGHT - MARGIN,
+        HEADER_SIZE,
+        &template.header,
+    );
+    for (index, line) in wrap_text(&template.footer, FOOTER_SIZE, PDF_PAGE_WIDTH - MARGIN * 2.0)
+        .iter()
+        .take(3)
+        .enumerate()
+    {
+        page.text(
+            MARGIN,
+            MARGIN - index as f32 * FOOTER_SIZE * 1.5,
+            FOOTER_SIZE,
+            line,
+        );
+    }
+    page.line(
+        MARGIN,
+        MARGIN + FOOTER_SIZE * 1.5,
+        PDF_PAGE_WIDTH - MARGIN,
+        MARGIN + FOOTER_SIZE * 1.5,
+    );
+    page.gray(0.0);
+    page
+}
+
+/// Lays the template out on A4 pages, with the verification QR code on the first one.
+pub fn render_report_pdf(
+    template: &ReportTemplate,
+    data: &ReportRenderData,
+) -> Result<Vec<u8>, GenericError> {
+    let mut document = PdfDocument::default();
+    let mut page = start_page(template);
+    let qrcode_x = PDF_PAGE_WIDTH - MARGIN - QRCODE_SIZE;
+    let qrcode_y = PDF_PAGE_HEIGHT - MARGIN - QRCODE_SIZE - HEADER_SIZE;
+    draw_qrcode(&mut page, &data.verification_url, qrcode_x, qrcode_y)?;
+    page.text(qrcode_x + 8.0, qrcode_y - 12.0, FOOTER_SIZE, "扫码验证报告");
+    page.text(
+        MARGIN,
+        PDF_PAGE_HEIGHT - MARGIN - HEADER_SIZE - TITLE_SIZE * 2.0,
+        TITLE_SIZE,
+        &template.title,
+    );
+    // Body text starts below the QR code and stops above the footer
+    let top = qrcode_y - BODY_SIZE * 3.0;
+    let bottom = MARGIN + FOOTER_SIZE * 3.0;
+    let mut y = top;
+    for line in layout_body(template, data) {
+        let line_height = match &line {
+            RenderLine::Text(_, size) => size * 1.8,
+            _ => BODY_SIZE * 1.8,
+        };
+        if y - line_height < bottom {
+            document.add_page(page);
+            page = start_page(template);
+            y = PDF_PAGE_HEIGHT - MARGIN - HEADER_SIZE * 3.0;
+        }
+        y -= line_height;
+        match line {
+            RenderLine::Text(text, size) => page.text(MARGIN, y, size, &text),
+            RenderLine::ResultRow(cells, is_header) => {
+                for (cell, offset) in cells.iter().zip(RESULT_COLUMNS.iter()) {
+                    page.text(MARGIN + offset, y, BODY_SIZE, cell);
+                }
+                if is_header {
+                    page.line(
+                        MARGIN,
+                        y - BODY_SIZE * 0.5,
+                        PDF_PAGE_WIDTH - MARGIN,
+                        y - BODY_SIZE * 0.5,
+                    );
+                }
+            }
+            RenderLine::Blank => (),
+        }
+    }
+    document.add_page(page);
+    Ok(document.to_bytes())
+}
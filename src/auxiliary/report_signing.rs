@@ -0,0 +1,89 @@
+use openssl::cms::{CMSOptions, CmsContentInfo};
+use openssl::pkey::{PKey, Private};
+use openssl::stack::Stack;
+use openssl::x509::X509;
+
+use std::env;
+use std::fs;
+
+use crate::auxiliary::GenericError;
+
+pub struct ReportSigner {
+    key: PKey<Private>,
+    certificate: X509,
+}
+
+impl ReportSigner {
+    fn load() -> Option<Self> {
+        let (key_path, certificate_path) = match (
+            env::var("REPORT_SIGNING_KEY"),
+            env::var("REPORT_SIGNING_CERTIFICATE"),
+        ) {
+            (Ok(key_path), Ok(certificate_path)) => (key_path, certificate_path),
+            _ => {
+                warn!("未配置报告签名密钥，报告将不会签名");
+                return None;
+            }
+        };
+        let key = fs::read(&key_path).unwrap_or_else(|_| panic!("文件{}不存在", &key_path));
+        let certificate = fs::read(&certificate_path)
+            .unwrap_or_else(|_| panic!("文件{}不存在", &certificate_path));
+        Some(Self {
+            key: PKey::private_key_from_pem(&key).expect("报告签名密钥格式错误"),
+            certificate: X509::from_pem(&certificate).expect("报告签名证书格式错误"),
+        })
+    }
+}
+
+lazy_static! {
+    static ref REPORT_SIGNER: Option<ReportSigner> = ReportSigner::load();
+}
+
+pub fn is_report_signing_enabled() -> bool {
+    REPORT_SIGNER.is_some()
+}
+
+/// Detached CMS signature of the file in DER, `None` when no signing key is configured.
+pub fn sign_report_file(content: &[u8]) -> Result<Option<Vec<u8>>, GenericError> {
+    let signer = match REPORT_SIGNER.as_ref() {
+        Some(signer) => signer,
+        None => return Ok(None),
+    };
+    CmsContentInfo::sign(
+        Some(&signer.certificate),
+        Some(&signer.key),
+        None,
+        Some(content),
+        CMSOptions::DETACHED | CMSOptions::BINARY,
+    )
+    .and_then(|signature| signature.to_der())
+    .map(Some)
+    .map_err(|error| {
+        error!("签名报告时出错：{:?}", error);
+        GenericError::ServerInternalError
+    })
+}
+
+/// Only our own certificate is accepted as the signer, whatever the signature carries.
+pub fn verify_report_signature(content: &[u8], signature: &[u8]) -> Result<bool, GenericError> {
+    let signer = REPORT_SIGNER
+        .as_ref()
+        .ok_or(GenericError::ReportSigningNotConfiguredError)?;
+    let mut signature = match CmsContentInfo::from_der(signature) {
+        Ok(signature) => signature,
+        Err(_) => return Ok(false),
+    };
+    let mut certificates = Stack::new().map_err(|_| GenericError::ServerInternalError)?;
+    certificates
+        .push(signer.certificate.to_owned())
+        .map_err(|_| GenericError::ServerInternalError)?;
+    Ok(signature
+        .verify(
+            Some(&certificates),
+            None,
+            Some(content),
+            None,
+            CMSOptions::BINARY | CMSOptions::NOINTERN | CMSOptions::NO_SIGNER_CERT_VERIFY,
+        )
+        .is_ok())
+}
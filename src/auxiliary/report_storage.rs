@@ -0,0 +1,124 @@
+use chrono::prelude::*;
+use chrono::NaiveDateTime;
+
+use rocket::tokio::fs;
+
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::auxiliary::{read_config_or, GenericError, S3ReportStorage};
+
+pub struct StoredReportFile {
+    pub key: String,
+    pub modified_time: Option<NaiveDateTime>,
+}
+
+/// Report files are addressed by a flat key such as `<report_id>.pdf`.
+#[rocket::async_trait]
+pub trait ReportStorage: Send + Sync {
+    async fn put(&self, key: &str, content: Vec<u8>) -> Result<(), GenericError>;
+    /// Returns `None` when there is no file under the key.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, GenericError>;
+    async fn delete(&self, key: &str) -> Result<(), GenericError>;
+    /// Every file in the storage, for reconciling it against the reports table.
+    async fn list(&self) -> Result<Vec<StoredReportFile>, GenericError>;
+}
+
+// Keys of published reports come from request data, so anything path-like is refused
+pub fn is_valid_report_key(key: &str) -> bool {
+    !key.is_empty()
+        && key != "."
+        && key != ".."
+        && !key.contains(|c: char| c == '/' || c == '\\' || c.is_control())
+}
+
+pub struct LocalReportStorage {
+    root: PathBuf,
+}
+
+impl LocalReportStorage {
+    pub fn load() -> Self {
+        let mut root = env::current_dir().expect("工作路径获取失败");
+        root.push(env::var("REPORT_PATH").expect("未设置REPORT_PATH"));
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, GenericError> {
+        if !is_valid_report_key(key) {
+            return Err(GenericError::InvalidInputError);
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+fn local_storage_error(error: std::io::Error) -> GenericError {
+    error!("读写报告文件时出错：{:?}", error);
+    GenericError::ReportStorageError
+}
+
+#[rocket::async_trait]
+impl ReportStorage for LocalReportStorage {
+    async fn put(&self, key: &str, content: Vec<u8>) -> Result<(), GenericError> {
+        let path = self.path(key)?;
+        fs::create_dir_all(&self.root)
+            .await
+            .map_err(local_storage_error)?;
+        fs::write(path, content).await.map_err(local_storage_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, GenericError> {
+        match fs::read(self.path(key)?).await {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(local_storage_error(error)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), GenericError> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(local_storage_error(error)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<StoredReportFile>, GenericError> {
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(local_storage_error(error)),
+        };
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(local_storage_error)? {
+            let metadata = entry.metadata().await.map_err(local_storage_error)?;
+            let key = match entry.file_name().into_string() {
+                Ok(key) if metadata.is_file() => key,
+                _ => continue,
+            };
+            files.push(StoredReportFile {
+                key,
+                modified_time: metadata
+                    .modified()
+                    .ok()
+                    .map(|modified_time| DateTime::<Utc>::from(modified_time).naive_utc()),
+            });
+        }
+        Ok(files)
+    }
+}
+
+pub struct ReportStorageState {
+    pub storage: Arc<dyn ReportStorage>,
+}
+
+impl ReportStorageState {
+    pub fn load() -> Self {
+        let storage: Arc<dyn ReportStorage> =
+            match read_config_or("REPORT_STORAGE", "local".to_string()).as_ref() {
+                "s3" => Arc::new(S3ReportStorage::load()),
+                _ => Arc::new(LocalReportStorage::load()),
+            };
+        Self { storage }
+    }
+}
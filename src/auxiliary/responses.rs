@@ -25,7 +25,61 @@ pub enum GenericError {
     GetWechatAccessTokenError,
     GetWechatOpenIdError,
     GetWechatUserinfoError,
+    GetWechatSessionError,
+    WechatSignatureError,
+    WechatMessageParseError,
+    InvalidEmailError,
+    InvalidWebhookUrlError,
+    WebhookUrlNotAllowedError,
+    PhoneNumberNotBoundError,
     ProfileNotExistError,
+    PasswordEmptyError,
+    PasswordTooShortError,
+    PasswordMissingLowercaseError,
+    PasswordMissingUppercaseError,
+    PasswordMissingDigitError,
+    PasswordMissingSymbolError,
+    PasswordTooCommonError,
+    PasswordUnchangedError,
+    TooManyRequestsError,
+    AccountLockedError,
+    InvalidPhoneNumberError,
+    SmsSendError,
+    SmsCodeTooFrequentError,
+    SmsCodeIncorrectError,
+    SmsCodeExpiredError,
+    TotpPendingError,
+    TotpNotPendingError,
+    TotpRequiredError,
+    TotpCodeIncorrectError,
+    TotpNotEnrolledError,
+    TotpAlreadyEnabledError,
+    WechatAlreadyBoundError,
+    WechatAppAlreadyBoundError,
+    WechatNotBoundError,
+    LastLoginMethodError,
+    MergeConflictError,
+    ReportLinkInvalidError,
+    ReportLinkExpiredError,
+    ReportFileNotFoundError,
+    ReportFileTooLargeError,
+    ReportStorageError,
+    ReportNotPdfError,
+    ReportPdfMalformedError,
+    ReportPdfEncryptedError,
+    ReportPdfScriptError,
+    ReportAmendmentReasonRequiredError,
+    ReportRetractedError,
+    ReportNoLongerValidError,
+    ReportPendingReviewError,
+    ReportNotPendingReviewError,
+    ReportSelfReviewError,
+    ReportTemplateNotFoundError,
+    ReportVerificationCodeInvalidError,
+    ReportSigningNotConfiguredError,
+    TestTypeNotFoundError,
+    ReportTestTypeNotExpectedError,
+    ProductKitTypeLockedError,
 }
 
 #[derive(Serialize)]
@@ -55,6 +109,10 @@ pub type GenericResult<T> = Result<Json<SuccessResponse<T>>, GenericError>;
 impl<'a> Responder<'a, 'static> for GenericError {
     fn respond_to(self, req: &'a Request<'_>) -> response::Result<'static> {
         info!("{:?}", self);
+        let status = match self {
+            Self::TooManyRequestsError => Status::TooManyRequests,
+            _ => Status::InternalServerError,
+        };
         let error_message = match self {
             Self::DieselError(inner_error) => match inner_error {
                 DieselError::NotFound => "请求的资源不存在",
@@ -74,7 +132,61 @@ impl<'a> Responder<'a, 'static> for GenericError {
             Self::GetWechatAccessTokenError => "微信AccessToken获取失败",
             Self::GetWechatOpenIdError => "微信OpenId获取失败",
             Self::GetWechatUserinfoError => "微信Userinfo获取失败",
+            Self::GetWechatSessionError => "微信小程序登录失败",
+            Self::WechatSignatureError => "微信签名校验失败",
+            Self::WechatMessageParseError => "微信消息解析失败",
+            Self::InvalidEmailError => "邮箱地址格式错误",
+            Self::InvalidWebhookUrlError => "Webhook地址格式错误",
+            Self::WebhookUrlNotAllowedError => "Webhook地址须为可公开访问的HTTPS地址",
+            Self::PhoneNumberNotBoundError => "未绑定手机号",
             Self::ProfileNotExistError => "档案未填写",
+            Self::PasswordEmptyError => "密码不能为空",
+            Self::PasswordTooShortError => "密码长度不足",
+            Self::PasswordMissingLowercaseError => "密码须包含小写字母",
+            Self::PasswordMissingUppercaseError => "密码须包含大写字母",
+            Self::PasswordMissingDigitError => "密码须包含数字",
+            Self::PasswordMissingSymbolError => "密码须包含特殊字符",
+            Self::PasswordTooCommonError => "密码过于常见",
+            Self::PasswordUnchangedError => "新密码不能与原密码相同",
+            Self::TooManyRequestsError => "请求过于频繁，请稍后再试",
+            Self::AccountLockedError => "登录失败次数过多，账户已被暂时锁定",
+            Self::InvalidPhoneNumberError => "手机号格式错误",
+            Self::SmsSendError => "短信发送失败",
+            Self::SmsCodeTooFrequentError => "验证码发送过于频繁",
+            Self::SmsCodeIncorrectError => "验证码错误",
+            Self::SmsCodeExpiredError => "验证码已失效，请重新获取",
+            Self::TotpPendingError => "请先完成两步验证",
+            Self::TotpNotPendingError => "当前无需两步验证",
+            Self::TotpRequiredError => "该账户须启用两步验证",
+            Self::TotpCodeIncorrectError => "两步验证码错误",
+            Self::TotpNotEnrolledError => "尚未设置两步验证",
+            Self::TotpAlreadyEnabledError => "两步验证已启用",
+            Self::WechatAlreadyBoundError => "该微信已绑定其他账户",
+            Self::WechatAppAlreadyBoundError => "该账户已绑定其他微信",
+            Self::WechatNotBoundError => "当前账户未绑定微信",
+            Self::LastLoginMethodError => "解绑后将无法登录，请先设置密码或手机号",
+            Self::MergeConflictError => "两个账户的登录方式存在冲突",
+            Self::ReportLinkInvalidError => "报告链接无效",
+            Self::ReportLinkExpiredError => "报告链接已过期",
+            Self::ReportFileNotFoundError => "报告文件不存在",
+            Self::ReportFileTooLargeError => "报告文件过大",
+            Self::ReportStorageError => "报告存储服务出错",
+            Self::ReportNotPdfError => "报告文件不是PDF格式",
+            Self::ReportPdfMalformedError => "报告PDF文件已损坏",
+            Self::ReportPdfEncryptedError => "报告PDF文件不能加密",
+            Self::ReportPdfScriptError => "报告PDF文件不能包含脚本",
+            Self::ReportAmendmentReasonRequiredError => "更正报告需填写更正原因",
+            Self::ReportRetractedError => "报告已撤回",
+            Self::ReportNoLongerValidError => "报告已被更正或撤回",
+            Self::ReportPendingReviewError => "该试剂盒已有待审核的报告",
+            Self::ReportNotPendingReviewError => "报告不在待审核状态",
+            Self::ReportSelfReviewError => "不能审核自己上传的报告",
+            Self::ReportTemplateNotFoundError => "报告模板不存在",
+            Self::ReportVerificationCodeInvalidError => "报告验证码无效",
+            Self::ReportSigningNotConfiguredError => "未配置报告签名证书",
+            Self::TestTypeNotFoundError => "检测项目不存在",
+            Self::ReportTestTypeNotExpectedError => "该试剂盒类型不包含此检测项目",
+            Self::ProductKitTypeLockedError => "试剂盒已有报告，不能更改类型",
         }
         .to_string();
         let mut json_result = Json(ErrorResponse {
@@ -84,7 +196,7 @@ impl<'a> Responder<'a, 'static> for GenericError {
         })
         .respond_to(&req)
         .unwrap();
-        json_result.set_status(Status::InternalServerError);
+        json_result.set_status(status);
         Response::build_from(json_result).ok()
     }
 }
@@ -0,0 +1,282 @@
+use chrono::prelude::*;
+
+use data_encoding::HEXLOWER;
+
+use hmac::{Hmac, Mac, NewMac};
+
+use isahc::config::Configurable;
+use isahc::http::{Method, Uri};
+use isahc::{self, AsyncReadResponseExt, Request};
+
+use serde::Deserialize;
+
+use sha2::{Digest, Sha256};
+
+use std::env;
+use std::time::Duration;
+
+use crate::auxiliary::{
+    is_valid_report_key, read_config_or, GenericError, ReportStorage, StoredReportFile,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Path-style client for S3-compatible object storage, signed with AWS Signature Version 4.
+pub struct S3ReportStorage {
+    endpoint: String,
+    host: String,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    key_prefix: String,
+    timeout: Duration,
+}
+
+fn hmac_sha256(key: &[u8], content: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC可接受任意长度的密钥");
+    mac.update(content.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    HEXLOWER.encode(&Sha256::digest(content))
+}
+
+// RFC 3986 encoding as required by SigV4, keeping the slashes between path segments
+fn encode_uri_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn encode_uri_component(value: &str) -> String {
+    encode_uri_path(value).replace('/', "%2F")
+}
+
+#[derive(Deserialize)]
+struct ListBucketResult {
+    #[serde(rename = "Contents", default)]
+    contents: Vec<ListedObject>,
+    #[serde(rename = "IsTruncated", default)]
+    is_truncated: bool,
+    #[serde(rename = "NextContinuationToken")]
+    next_continuation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListedObject {
+    #[serde(rename = "Key")]
+    key: String,
+    #[serde(rename = "LastModified")]
+    last_modified: Option<String>,
+}
+
+fn s3_error(error: impl std::fmt::Debug) -> GenericError {
+    error!("访问对象存储时出错：{:?}", error);
+    GenericError::ReportStorageError
+}
+
+impl S3ReportStorage {
+    pub fn load() -> Self {
+        let endpoint = env::var("S3_ENDPOINT")
+            .expect("未设置S3_ENDPOINT")
+            .trim_end_matches('/')
+            .to_string();
+        let host = endpoint
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.authority().map(|authority| authority.to_string()))
+            .expect("S3_ENDPOINT格式错误");
+        Self {
+            endpoint,
+            host,
+            bucket: env::var("S3_BUCKET").expect("未设置S3_BUCKET"),
+            region: read_config_or("S3_REGION", "us-east-1".to_string()),
+            access_key_id: env::var("S3_ACCESS_KEY_ID").expect("未设置S3_ACCESS_KEY_ID"),
+            secret_access_key: env::var("S3_SECRET_ACCESS_KEY")
+                .expect("未设置S3_SECRET_ACCESS_KEY"),
+            key_prefix: read_config_or("S3_KEY_PREFIX", "reports/".to_string()),
+            timeout: Duration::from_secs(read_config_or("S3_TIMEOUT_SECONDS", 30)),
+        }
+    }
+
+    fn authorization(
+        &self,
+        method: &Method,
+        canonical_uri: &str,
+        canonical_query: &str,
+        amz_date: &str,
+        payload_hash: &str,
+    ) -> String {
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            canonical_uri,
+            canonical_query,
+            self.host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let date_key = hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), date);
+        let region_key = hmac_sha256(&date_key, &self.region);
+        let service_key = hmac_sha256(&region_key, "s3");
+        let signing_key = hmac_sha256(&service_key, "aws4_request");
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id,
+            scope,
+            signed_headers,
+            HEXLOWER.encode(&hmac_sha256(&signing_key, &string_to_sign))
+        )
+    }
+
+    /// Returns the response status code and body.
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        content: Vec<u8>,
+    ) -> Result<(u16, Vec<u8>), GenericError> {
+        if !is_valid_report_key(key) {
+            return Err(GenericError::InvalidInputError);
+        }
+        let canonical_uri =
+            encode_uri_path(&format!("/{}/{}{}", self.bucket, self.key_prefix, key));
+        self.send_request(method, &canonical_uri, "", content).await
+    }
+
+    /// The query string must already be canonical, sorted and encoded.
+    async fn send_request(
+        &self,
+        method: Method,
+        canonical_uri: &str,
+        canonical_query: &str,
+        content: Vec<u8>,
+    ) -> Result<(u16, Vec<u8>), GenericError> {
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = sha256_hex(&content);
+        let authorization = self.authorization(
+            &method,
+            canonical_uri,
+            canonical_query,
+            &amz_date,
+            &payload_hash,
+        );
+        let uri = match canonical_query {
+            "" => format!("{}{}", self.endpoint, canonical_uri),
+            _ => format!("{}{}?{}", self.endpoint, canonical_uri, canonical_query),
+        };
+        let mut request = Request::builder()
+            .method(method.clone())
+            .uri(uri)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization)
+            .timeout(self.timeout);
+        if method == Method::PUT {
+            request = request.header("Content-Type", "application/pdf");
+        }
+        let mut response = isahc::send_async(request.body(content).map_err(s3_error)?)
+            .await
+            .map_err(s3_error)?;
+        let status_code = response.status().as_u16();
+        let mut body = Vec::new();
+        if method == Method::GET && status_code == 200 {
+            response.copy_to(&mut body).await.map_err(s3_error)?;
+        }
+        Ok((status_code, body))
+    }
+}
+
+fn unexpected_status(status_code: u16) -> GenericError {
+    s3_error(format!("HTTP {}", status_code))
+}
+
+#[rocket::async_trait]
+impl ReportStorage for S3ReportStorage {
+    async fn put(&self, key: &str, content: Vec<u8>) -> Result<(), GenericError> {
+        match self.send(Method::PUT, key, content).await? {
+            (200..=299, _) => Ok(()),
+            (status_code, _) => Err(unexpected_status(status_code)),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, GenericError> {
+        match self.send(Method::GET, key, Vec::new()).await? {
+            (200, content) => Ok(Some(content)),
+            (404, _) => Ok(None),
+            (status_code, _) => Err(unexpected_status(status_code)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), GenericError> {
+        match self.send(Method::DELETE, key, Vec::new()).await? {
+            (200..=299, _) | (404, _) => Ok(()),
+            (status_code, _) => Err(unexpected_status(status_code)),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<StoredReportFile>, GenericError> {
+        let canonical_uri = encode_uri_path(&format!("/{}", self.bucket));
+        let mut files = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            // Parameters of a canonical query string are sorted by name
+            let mut canonical_query = String::new();
+            if let Some(continuation_token) = &continuation_token {
+                canonical_query.push_str(&format!(
+                    "continuation-token={}&",
+                    encode_uri_component(continuation_token)
+                ));
+            }
+            canonical_query.push_str(&format!(
+                "list-type=2&prefix={}",
+                encode_uri_component(&self.key_prefix)
+            ));
+            let body = match self
+                .send_request(Method::GET, &canonical_uri, &canonical_query, Vec::new())
+                .await?
+            {
+                (200, body) => body,
+                (status_code, _) => return Err(unexpected_status(status_code)),
+            };
+            let result: ListBucketResult =
+                quick_xml::de::from_str(&String::from_utf8_lossy(&body)).map_err(s3_error)?;
+            for object in result.contents {
+                // Anything nested deeper than the prefix was not put there by us
+                match object.key.strip_prefix(&self.key_prefix) {
+                    Some(key) if is_valid_report_key(key) => files.push(StoredReportFile {
+                        key: key.to_string(),
+                        modified_time: object
+                            .last_modified
+                            .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+                            .map(|time| time.naive_utc()),
+                    }),
+                    _ => continue,
+                }
+            }
+            match result.next_continuation_token {
+                Some(next_token) if result.is_truncated => continuation_token = Some(next_token),
+                _ => break,
+            }
+        }
+        Ok(files)
+    }
+}
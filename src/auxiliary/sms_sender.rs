@@ -0,0 +1,83 @@
+use chrono::prelude::*;
+
+use rocket::tokio::fs::OpenOptions;
+use rocket::tokio::io::AsyncWriteExt;
+
+use serde::Serialize;
+
+use std::sync::Arc;
+
+use crate::auxiliary::{read_config_or, GenericError};
+
+#[rocket::async_trait]
+pub trait SmsSender: Send + Sync {
+    async fn send(&self, phone_number: &str, content: &str) -> Result<(), GenericError>;
+}
+
+pub struct LogSmsSender;
+
+#[rocket::async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, phone_number: &str, content: &str) -> Result<(), GenericError> {
+        info!("短信发送至{}：{}", phone_number, content);
+        Ok(())
+    }
+}
+
+pub struct FileSmsSender {
+    outbox_path: String,
+}
+
+#[derive(Serialize)]
+struct FileSmsRecord<'a> {
+    send_time: NaiveDateTime,
+    phone_number: &'a str,
+    content: &'a str,
+}
+
+#[rocket::async_trait]
+impl SmsSender for FileSmsSender {
+    async fn send(&self, phone_number: &str, content: &str) -> Result<(), GenericError> {
+        let record = FileSmsRecord {
+            send_time: Utc::now().naive_utc(),
+            phone_number,
+            content,
+        };
+        let mut line = serde_json::to_string(&record).map_err(|_| GenericError::SmsSendError)?;
+        line.push('\n');
+        let mut outbox_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.outbox_path)
+            .await
+            .map_err(|error| {
+                error!("打开短信发件箱文件时出错：{:?}", error);
+                GenericError::SmsSendError
+            })?;
+        outbox_file
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|error| {
+                error!("写入短信发件箱文件时出错：{:?}", error);
+                GenericError::SmsSendError
+            })
+    }
+}
+
+pub struct SmsSenderState {
+    pub sender: Arc<dyn SmsSender>,
+}
+
+impl SmsSenderState {
+    pub fn load() -> Self {
+        let sender: Arc<dyn SmsSender> = match read_config_or("SMS_PROVIDER", "log".to_string())
+            .as_ref()
+        {
+            "file" => Arc::new(FileSmsSender {
+                outbox_path: read_config_or("SMS_OUTBOX_FILE", "./sms_outbox.jsonl".to_string()),
+            }),
+            _ => Arc::new(LogSmsSender),
+        };
+        Self { sender }
+    }
+}
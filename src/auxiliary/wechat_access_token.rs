@@ -1,20 +1,32 @@
 use chrono::prelude::*;
 use chrono::{DateTime, Duration};
 
-use isahc::{self, AsyncReadResponseExt, ReadResponseExt};
+use isahc::{self, AsyncReadResponseExt};
 
 use log::error;
-use rocket::tokio::sync::RwLock;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::tokio::sync::{Mutex, RwLock};
+use rocket::tokio::time::sleep;
+use rocket::{Orbit, Rocket};
 use serde::Deserialize;
 
 use std::env;
+use std::sync::Arc;
 
-use crate::auxiliary::GenericError;
+use crate::auxiliary::{read_config_or, GenericError};
 
 lazy_static! {
     pub static ref WECHAT_APPID: String = env::var("WECHAT_APPID").expect("未设置WECHAT_APPID");
     pub static ref WECHAT_APPSECRET: String =
         env::var("WECHAT_APPSECRET").expect("未设置WECHAT_APPSECRET");
+    pub static ref WECHAT_API_BASE: String =
+        read_config_or("WECHAT_API_BASE", "https://api.weixin.qq.com".to_string())
+            .trim_end_matches('/')
+            .to_string();
+    static ref WECHAT_ACCESS_TOKEN_BACKGROUND_REFRESH: bool =
+        read_config_or("WECHAT_ACCESS_TOKEN_BACKGROUND_REFRESH", true);
+    static ref WECHAT_ACCESS_TOKEN_RETRY_SECONDS: i64 =
+        read_config_or("WECHAT_ACCESS_TOKEN_RETRY_SECONDS", 60).max(1);
 }
 
 pub struct WechatAccessToken {
@@ -22,8 +34,16 @@ pub struct WechatAccessToken {
     expiration_time: DateTime<Utc>,
 }
 
+impl WechatAccessToken {
+    fn is_valid(&self) -> bool {
+        Utc::now() < self.expiration_time
+    }
+}
+
+#[derive(Clone)]
 pub struct WechatAccessTokenState {
-    pub state: RwLock<WechatAccessToken>,
+    state: Arc<RwLock<Option<WechatAccessToken>>>,
+    refetch_lock: Arc<Mutex<()>>,
 }
 
 #[derive(Deserialize)]
@@ -33,45 +53,18 @@ struct WechatAccessTokenResponse {
 }
 
 impl WechatAccessTokenState {
-    pub fn new() -> Result<Self, GenericError> {
-        match isahc::get(format!(
-            "https://api.weixin.qq.com\
-            /cgi-bin/token?grant_type=client_credential&\
-            appid={}&secret={}",
-            *WECHAT_APPID, *WECHAT_APPSECRET
-        )) {
-            Ok(mut response) => match response.json::<WechatAccessTokenResponse>() {
-                Ok(parsed_response) => {
-                    let current_time = Utc::now();
-                    let expiration_time =
-                        current_time + Duration::seconds((parsed_response.expires_in - 360) as i64);
-                    Ok(Self {
-                        state: RwLock::new(WechatAccessToken {
-                            access_token: parsed_response.access_token,
-                            expiration_time,
-                        }),
-                    })
-                }
-                Err(error) => {
-                    error!("获取AccessToken时出错：{:?}", error);
-                    panic!()
-                }
-            },
-            Err(error) => {
-                error!("获取AccessToken时出错：{:?}", error);
-                panic!()
-            }
+    // Nothing is fetched until the token is first needed or the refresher starts
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(None)),
+            refetch_lock: Arc::new(Mutex::new(())),
         }
     }
 
-    async fn refetch(&self) -> Result<(), GenericError> {
+    async fn refetch(&self) -> Result<String, GenericError> {
         let parsed_response: WechatAccessTokenResponse = isahc::get_async(format!(
-            "\
-        https://api.weixin.qq.com/cgi-bin/token?\
-        grant_type=client_credential&\
-        appid={}&secret={}\
-        ",
-            *WECHAT_APPID, *WECHAT_APPSECRET
+            "{}/cgi-bin/token?grant_type=client_credential&appid={}&secret={}",
+            *WECHAT_API_BASE, *WECHAT_APPID, *WECHAT_APPSECRET
         ))
         .await
         .map_err(|error| {
@@ -86,19 +79,81 @@ impl WechatAccessTokenState {
         })?;
         let current_time = Utc::now();
         let expiration_time =
-            current_time + Duration::seconds((parsed_response.expires_in - 360) as i64);
+            current_time + Duration::seconds((parsed_response.expires_in - 360).max(0) as i64);
         let mut writer = self.state.write().await;
-        writer.expiration_time = expiration_time;
-        writer.access_token = parsed_response.access_token;
-        Ok(())
+        *writer = Some(WechatAccessToken {
+            access_token: parsed_response.access_token.to_owned(),
+            expiration_time,
+        });
+        Ok(parsed_response.access_token)
+    }
+
+    async fn cached(&self) -> Option<String> {
+        match &*self.state.read().await {
+            Some(token) if token.is_valid() => Some(token.access_token.to_owned()),
+            _ => None,
+        }
     }
 
     pub async fn get(&self) -> Result<String, GenericError> {
-        let current_time = Utc::now();
-        let valid_time = self.state.read().await.expiration_time;
-        if current_time > valid_time {
-            self.refetch().await?;
+        if let Some(access_token) = self.cached().await {
+            return Ok(access_token);
+        }
+        // Only one request refetches, the others wait and reuse its result
+        let _guard = self.refetch_lock.lock().await;
+        if let Some(access_token) = self.cached().await {
+            return Ok(access_token);
+        }
+        self.refetch().await
+    }
+
+    // For when WeChat rejects a token before its expected expiration
+    pub async fn invalidate(&self) {
+        *self.state.write().await = None;
+    }
+
+    async fn expiration_time(&self) -> Option<DateTime<Utc>> {
+        self.state
+            .read()
+            .await
+            .as_ref()
+            .map(|token| token.expiration_time)
+    }
+
+    async fn refresh_forever(self) {
+        loop {
+            let wait_seconds = match self.get().await {
+                Ok(_) => match self.expiration_time().await {
+                    Some(expiration_time) => (expiration_time - Utc::now()).num_seconds().max(1),
+                    None => *WECHAT_ACCESS_TOKEN_RETRY_SECONDS,
+                },
+                Err(_) => *WECHAT_ACCESS_TOKEN_RETRY_SECONDS,
+            };
+            sleep(std::time::Duration::from_secs(wait_seconds as u64)).await;
+        }
+    }
+}
+
+pub struct WechatAccessTokenRefresher;
+
+#[rocket::async_trait]
+impl Fairing for WechatAccessTokenRefresher {
+    fn info(&self) -> Info {
+        Info {
+            name: "WeChat access token refresher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        if !*WECHAT_ACCESS_TOKEN_BACKGROUND_REFRESH {
+            return;
+        }
+        match rocket.state::<WechatAccessTokenState>() {
+            Some(state) => {
+                rocket::tokio::spawn(state.clone().refresh_forever());
+            }
+            None => error!("WechatAccessTokenState未加载"),
         }
-        Ok(self.state.read().await.access_token.to_owned())
     }
 }
@@ -0,0 +1,81 @@
+use isahc::{self, AsyncReadResponseExt};
+
+use std::env;
+
+use crate::auxiliary::{GenericError, WECHAT_API_BASE, WECHAT_APPID, WECHAT_APPSECRET};
+use crate::models::{WechatOpenIdResponse, WechatSessionResponse, WechatUserinfoResponse};
+
+lazy_static! {
+    pub static ref WECHAT_MINIPROGRAM_APPID: Option<String> =
+        env::var("WECHAT_MINIPROGRAM_APPID").ok();
+    static ref WECHAT_MINIPROGRAM_APPSECRET: Option<String> =
+        env::var("WECHAT_MINIPROGRAM_APPSECRET").ok();
+}
+
+pub async fn fetch_wechat_openid(code: &str) -> Result<WechatOpenIdResponse, GenericError> {
+    isahc::get_async(format!(
+        "{}/sns/oauth2/access_token?\
+    appid={}&secret={}&code={}&grant_type=authorization_code\
+    ",
+        *WECHAT_API_BASE, *WECHAT_APPID, *WECHAT_APPSECRET, code
+    ))
+    .await
+    .map_err(|error| {
+        error!("获取OpenId时出错：{:?}", error);
+        GenericError::GetWechatOpenIdError
+    })?
+    .json()
+    .await
+    .map_err(|error| {
+        error!("获取OpenId时出错：{:?}", error);
+        GenericError::GetWechatOpenIdError
+    })
+}
+
+pub async fn fetch_wechat_userinfo(
+    access_token: &str,
+    openid: &str,
+) -> Result<WechatUserinfoResponse, GenericError> {
+    isahc::get_async(format!(
+        "{}/sns/userinfo?access_token={}&openid={}&lang=zh_CN",
+        *WECHAT_API_BASE, access_token, openid
+    ))
+    .await
+    .map_err(|error| {
+        error!("获取Userinfo时出错：{:?}", error);
+        GenericError::GetWechatUserinfoError
+    })?
+    .json()
+    .await
+    .map_err(|error| {
+        error!("获取Userinfo时出错：{:?}", error);
+        GenericError::GetWechatUserinfoError
+    })
+}
+
+pub async fn fetch_wechat_session(code: &str) -> Result<WechatSessionResponse, GenericError> {
+    let (appid, appsecret) = match (&*WECHAT_MINIPROGRAM_APPID, &*WECHAT_MINIPROGRAM_APPSECRET) {
+        (Some(appid), Some(appsecret)) => (appid, appsecret),
+        _ => {
+            error!("未设置WECHAT_MINIPROGRAM_APPID或WECHAT_MINIPROGRAM_APPSECRET");
+            return Err(GenericError::GetWechatSessionError);
+        }
+    };
+    isahc::get_async(format!(
+        "{}/sns/jscode2session?\
+    appid={}&secret={}&js_code={}&grant_type=authorization_code\
+    ",
+        *WECHAT_API_BASE, appid, appsecret, code
+    ))
+    .await
+    .map_err(|error| {
+        error!("获取小程序Session时出错：{:?}", error);
+        GenericError::GetWechatSessionError
+    })?
+    .json()
+    .await
+    .map_err(|error| {
+        error!("获取小程序Session时出错：{:?}", error);
+        GenericError::GetWechatSessionError
+    })
+}
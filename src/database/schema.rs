@@ -2,6 +2,150 @@ table! {
     use diesel::sql_types::*;
     use crate::models::Stage;
     use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    jobs (id) {
+        id -> Int4,
+        job_type -> Varchar,
+        payload -> Text,
+        status -> Job_status,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        last_error -> Nullable<Text>,
+        unique_key -> Nullable<Varchar>,
+        interval_seconds -> Nullable<Int4>,
+        created_time -> Timestamp,
+        run_at -> Timestamp,
+        finished_time -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    kit_type_tests (kit_type_id, test_type_id) {
+        kit_type_id -> Int4,
+        test_type_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    kit_types (id) {
+        id -> Int4,
+        name -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    lockout_events (id) {
+        id -> Int4,
+        user_id -> Int4,
+        client_ip -> Nullable<Varchar>,
+        event_time -> Timestamp,
+        locked_until -> Timestamp,
+        cleared_by -> Nullable<Int4>,
+        cleared_time -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    notification_preferences (user_id) {
+        user_id -> Int4,
+        wechat_enabled -> Bool,
+        email_enabled -> Bool,
+        email -> Nullable<Varchar>,
+        sms_enabled -> Bool,
+        webhook_enabled -> Bool,
+        webhook_url -> Nullable<Varchar>,
+        updated_time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    notifications (id) {
+        id -> Int4,
+        user_id -> Int4,
+        product_id -> Int4,
+        channel -> Notification_channel,
+        recipient -> Varchar,
+        event_stage -> Stage,
+        payload -> Text,
+        status -> Notification_status,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        created_time -> Timestamp,
+        next_attempt_time -> Timestamp,
+        sent_time -> Nullable<Timestamp>,
+        reminder_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    product_reminders (id) {
+        id -> Int4,
+        product_id -> Int4,
+        stage -> Stage,
+        created_time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
 
     products (id) {
         id -> Int4,
@@ -9,7 +153,7 @@ table! {
         profile_id -> Nullable<Int4>,
         init_time -> Timestamp,
         current_stage -> Stage,
-        report_id -> Nullable<Uuid>,
+        kit_type_id -> Nullable<Int4>,
     }
 }
 
@@ -17,6 +161,10 @@ table! {
     use diesel::sql_types::*;
     use crate::models::Stage;
     use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
 
     profiles (id) {
         id -> Int4,
@@ -36,6 +184,67 @@ table! {
     use diesel::sql_types::*;
     use crate::models::Stage;
     use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    rate_limit_buckets (bucket_key, window_start) {
+        bucket_key -> Varchar,
+        window_start -> Timestamp,
+        hits -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    report_downloads (id) {
+        id -> Int4,
+        report_id -> Uuid,
+        user_id -> Nullable<Int4>,
+        shared_by -> Nullable<Int4>,
+        client_ip -> Nullable<Varchar>,
+        download_time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    report_templates (id) {
+        id -> Int4,
+        name -> Varchar,
+        title -> Varchar,
+        header -> Varchar,
+        body -> Text,
+        footer -> Varchar,
+        is_default -> Bool,
+        updated_by -> Nullable<Int4>,
+        updated_time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
 
     reports (id) {
         id -> Uuid,
@@ -43,6 +252,67 @@ table! {
         filename -> Nullable<Varchar>,
         download_url -> Varchar,
         upload_time -> Timestamp,
+        sha256 -> Nullable<Varchar>,
+        file_size -> Nullable<Int8>,
+        page_count -> Nullable<Int4>,
+        original_filename -> Nullable<Varchar>,
+        result_summary -> Nullable<Text>,
+        technician_comments -> Nullable<Text>,
+        lab_accession_number -> Nullable<Varchar>,
+        product_id -> Nullable<Int4>,
+        version -> Int4,
+        amendment_reason -> Nullable<Text>,
+        retracted_time -> Nullable<Timestamp>,
+        retracted_by -> Nullable<Int4>,
+        retraction_reason -> Nullable<Text>,
+        review_status -> Report_review_status,
+        reviewed_by -> Nullable<Int4>,
+        reviewed_time -> Nullable<Timestamp>,
+        review_comments -> Nullable<Text>,
+        template_id -> Nullable<Int4>,
+        result_items -> Nullable<Text>,
+        verification_code -> Varchar,
+        result_category -> Nullable<Varchar>,
+        signature -> Nullable<Bytea>,
+        test_type_id -> Nullable<Int4>,
+        is_current -> Bool,
+        amended -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    sms_codes (id) {
+        id -> Int4,
+        phone_number -> Varchar,
+        code_hashed -> Varchar,
+        created_time -> Timestamp,
+        expiration_time -> Timestamp,
+        attempts -> Int4,
+        consumed_time -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    test_types (id) {
+        id -> Int4,
+        code -> Varchar,
+        name -> Varchar,
     }
 }
 
@@ -50,6 +320,45 @@ table! {
     use diesel::sql_types::*;
     use crate::models::Stage;
     use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    totp_policies (user_role) {
+        user_role -> Role,
+        totp_required -> Bool,
+        updated_by -> Nullable<Int4>,
+        updated_time -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    totp_recovery_codes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        code_hashed -> Varchar,
+        created_time -> Timestamp,
+        used_time -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
 
     users (id) {
         id -> Int4,
@@ -57,19 +366,129 @@ table! {
         wechat_id -> Nullable<Varchar>,
         user_role -> Role,
         password_hashed -> Nullable<Varchar>,
-        phone_number -> Nullable<Int4>,
+        phone_number -> Nullable<Varchar>,
         sign_up_time -> Timestamp,
+        failed_login_count -> Int4,
+        lockout_count -> Int4,
+        locked_until -> Nullable<Timestamp>,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        totp_last_used_step -> Nullable<Int8>,
+        report_reviewer -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    webhook_deliveries (id) {
+        id -> Int4,
+        subscription_id -> Int4,
+        event_id -> Uuid,
+        event_type -> Varchar,
+        payload -> Text,
+        status -> Notification_status,
+        attempts -> Int4,
+        response_code -> Nullable<Int4>,
+        response_body -> Nullable<Text>,
+        last_error -> Nullable<Text>,
+        created_time -> Timestamp,
+        next_attempt_time -> Timestamp,
+        delivered_time -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    webhook_subscriptions (id) {
+        id -> Int4,
+        partner_name -> Varchar,
+        url -> Varchar,
+        secret -> Varchar,
+        event_types -> Array<Text>,
+        barcode_prefix -> Nullable<Varchar>,
+        enabled -> Bool,
+        created_by -> Int4,
+        created_time -> Timestamp,
+        updated_time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::models::Stage;
+    use crate::models::Role;
+    use crate::models::Notification_channel;
+    use crate::models::Notification_status;
+    use crate::models::Job_status;
+    use crate::models::Report_review_status;
+
+    wechat_identities (id) {
+        id -> Int4,
+        user_id -> Int4,
+        appid -> Varchar,
+        openid -> Varchar,
+        unionid -> Nullable<Varchar>,
+        created_time -> Timestamp,
     }
 }
 
+joinable!(kit_type_tests -> kit_types (kit_type_id));
+joinable!(kit_type_tests -> test_types (test_type_id));
+joinable!(lockout_events -> users (user_id));
+joinable!(notification_preferences -> users (user_id));
+joinable!(notifications -> product_reminders (reminder_id));
+joinable!(notifications -> products (product_id));
+joinable!(notifications -> users (user_id));
+joinable!(product_reminders -> products (product_id));
+joinable!(products -> kit_types (kit_type_id));
 joinable!(products -> profiles (profile_id));
-joinable!(products -> reports (report_id));
 joinable!(profiles -> users (user_id));
+joinable!(report_downloads -> reports (report_id));
+joinable!(report_templates -> users (updated_by));
+joinable!(reports -> products (product_id));
+joinable!(reports -> report_templates (template_id));
+joinable!(reports -> test_types (test_type_id));
 joinable!(reports -> users (uploader_id));
+joinable!(totp_policies -> users (updated_by));
+joinable!(totp_recovery_codes -> users (user_id));
+joinable!(webhook_deliveries -> webhook_subscriptions (subscription_id));
+joinable!(webhook_subscriptions -> users (created_by));
+joinable!(wechat_identities -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    jobs,
+    kit_type_tests,
+    kit_types,
+    lockout_events,
+    notification_preferences,
+    notifications,
+    product_reminders,
     products,
     profiles,
+    rate_limit_buckets,
+    report_downloads,
+    report_templates,
     reports,
+    sms_codes,
+    test_types,
+    totp_policies,
+    totp_recovery_codes,
     users,
+    webhook_deliveries,
+    webhook_subscriptions,
+    wechat_identities,
 );
@@ -0,0 +1,9 @@
+mod queue;
+mod registry;
+mod report_storage_check;
+mod worker;
+
+pub use queue::*;
+pub use registry::*;
+pub use report_storage_check::*;
+pub use worker::*;
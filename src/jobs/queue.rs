@@ -0,0 +1,232 @@
+use chrono::prelude::*;
+use chrono::Duration;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::auxiliary::read_config_or;
+use crate::database;
+use crate::jobs::JobContext;
+use crate::models::*;
+
+lazy_static! {
+    static ref JOB_BATCH_SIZE: i64 = read_config_or("JOB_BATCH_SIZE", 10);
+    static ref JOB_MAX_ATTEMPTS: i32 = read_config_or("JOB_MAX_ATTEMPTS", 5);
+    static ref JOB_RETRY_BASE_SECONDS: i64 = read_config_or("JOB_RETRY_BASE_SECONDS", 30);
+    static ref JOB_RETRY_MAX_SECONDS: i64 = read_config_or("JOB_RETRY_MAX_SECONDS", 3600);
+    // A running job whose lease runs out is assumed lost and gets picked up again
+    static ref JOB_LEASE_SECONDS: i64 = read_config_or("JOB_LEASE_SECONDS", 600);
+    static ref JOB_RETENTION_DAYS: i64 = read_config_or("JOB_RETENTION_DAYS", 30);
+}
+
+/// A unit of background work, stored as JSON under `JOB_TYPE` in the jobs table.
+#[rocket::async_trait]
+pub trait BackgroundJob: Serialize + DeserializeOwned + Send + 'static {
+    const JOB_TYPE: &'static str;
+
+    fn max_attempts() -> i32 {
+        *JOB_MAX_ATTEMPTS
+    }
+
+    async fn run(self, context: &JobContext) -> Result<(), String>;
+}
+
+fn serialize_job<J: BackgroundJob>(job: &J) -> QueryResult<String> {
+    serde_json::to_string(job)
+        .map_err(|error| diesel::result::Error::SerializationError(Box::new(error)))
+}
+
+pub fn enqueue_job<J: BackgroundJob>(c: &PgConnection, job: &J) -> QueryResult<Job> {
+    schedule_job(c, job, Utc::now().naive_utc())
+}
+
+pub fn schedule_job<J: BackgroundJob>(
+    c: &PgConnection,
+    job: &J,
+    run_at: NaiveDateTime,
+) -> QueryResult<Job> {
+    diesel::insert_into(database::jobs::table)
+        .values(NewJob {
+            job_type: J::JOB_TYPE.to_string(),
+            payload: serialize_job(job)?,
+            max_attempts: J::max_attempts(),
+            unique_key: None,
+            interval_seconds: None,
+            created_time: Utc::now().naive_utc(),
+            run_at,
+        })
+        .get_result(c)
+}
+
+pub fn new_recurring_job<J: BackgroundJob>(job: &J, interval_seconds: i32) -> QueryResult<NewJob> {
+    let current_time = Utc::now().naive_utc();
+    Ok(NewJob {
+        job_type: J::JOB_TYPE.to_string(),
+        payload: serialize_job(job)?,
+        max_attempts: J::max_attempts(),
+        unique_key: Some(J::JOB_TYPE.to_string()),
+        interval_seconds: Some(interval_seconds.max(1)),
+        created_time: current_time,
+        run_at: current_time,
+    })
+}
+
+/// Creates the single row of each recurring job, or updates its schedule if it already exists.
+pub fn register_recurring_jobs(c: &PgConnection, recurring_jobs: &[NewJob]) -> QueryResult<()> {
+    for recurring_job in recurring_jobs {
+        diesel::insert_into(database::jobs::table)
+            .values(recurring_job)
+            .on_conflict(database::jobs::unique_key)
+            .do_update()
+            .set((
+                database::jobs::payload.eq(&recurring_job.payload),
+                database::jobs::max_attempts.eq(recurring_job.max_attempts),
+                database::jobs::interval_seconds.eq(recurring_job.interval_seconds),
+            ))
+            .execute(c)?;
+    }
+    Ok(())
+}
+
+pub fn claim_due_jobs(c: &PgConnection) -> QueryResult<Vec<Job>> {
+    c.transaction(|| {
+        let current_time = Utc::now().naive_utc();
+        // One-off jobs that keep timing out are dead-lettered instead of being run again
+        diesel::update(
+            database::jobs::table
+                .filter(database::jobs::status.eq(JobStatusEnum::Running))
+                .filter(database::jobs::run_at.le(current_time))
+                .filter(database::jobs::attempts.ge(database::jobs::max_attempts))
+                .filter(database::jobs::interval_seconds.is_null()),
+        )
+        .set((
+            database::jobs::status.eq(JobStatusEnum::Dead),
+            database::jobs::last_error.eq("任务执行超时"),
+            database::jobs::finished_time.eq(current_time),
+        ))
+        .execute(c)?;
+        let due_ids: Vec<i32> = database::jobs::table
+            .filter(
+                database::jobs::status.eq_any(vec![JobStatusEnum::Pending, JobStatusEnum::Running]),
+            )
+            .filter(database::jobs::run_at.le(current_time))
+            .order(database::jobs::run_at)
+            .limit(*JOB_BATCH_SIZE)
+            .select(database::jobs::id)
+            .for_update()
+            .skip_locked()
+            .get_results(c)?;
+        diesel::update(database::jobs::table.filter(database::jobs::id.eq_any(due_ids)))
+            .set((
+                database::jobs::status.eq(JobStatusEnum::Running),
+                database::jobs::attempts.eq(database::jobs::attempts + 1),
+                database::jobs::run_at.eq(current_time + Duration::seconds(*JOB_LEASE_SECONDS)),
+            ))
+            .get_results(c)
+    })
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let multiplier = 2i64.saturating_pow((attempts - 1).max(0) as u32);
+    Duration::seconds(
+        JOB_RETRY_BASE_SECONDS
+            .saturating_mul(multiplier)
+            .min(*JOB_RETRY_MAX_SECONDS),
+    )
+}
+
+pub fn record_job_result(
+    c: &PgConnection,
+    job: &Job,
+    result: Result<(), String>,
+) -> QueryResult<usize> {
+    let current_time = Utc::now().naive_utc();
+    let target = database::jobs::table.find(job.id);
+    match (result, job.interval_seconds) {
+        (Ok(_), None) => diesel::update(target)
+            .set((
+                database::jobs::status.eq(JobStatusEnum::Succeeded),
+                database::jobs::last_error.eq(None::<String>),
+                database::jobs::finished_time.eq(Some(current_time)),
+            ))
+            .execute(c),
+        (Err(error), _) if job.attempts < job.max_attempts => diesel::update(target)
+            .set((
+                database::jobs::status.eq(JobStatusEnum::Pending),
+                database::jobs::last_error.eq(Some(error)),
+                database::jobs::run_at.eq(current_time + retry_delay(job.attempts)),
+            ))
+            .execute(c),
+        (Err(error), None) => {
+            warn!("任务{}（{}）多次执行失败：{}", job.id, job.job_type, error);
+            diesel::update(target)
+                .set((
+                    database::jobs::status.eq(JobStatusEnum::Dead),
+                    database::jobs::last_error.eq(Some(error)),
+                    database::jobs::finished_time.eq(Some(current_time)),
+                ))
+                .execute(c)
+        }
+        // Recurring jobs are never dead-lettered, they wait for their next turn instead
+        (result, Some(interval_seconds)) => {
+            let last_error = result.err();
+            if let Some(error) = &last_error {
+                warn!("定时任务{}多次执行失败：{}", job.job_type, error);
+            }
+            diesel::update(target)
+                .set((
+                    database::jobs::status.eq(JobStatusEnum::Pending),
+                    database::jobs::attempts.eq(0),
+                    database::jobs::last_error.eq(last_error),
+                    database::jobs::run_at
+                        .eq(current_time + Duration::seconds(interval_seconds as i64)),
+                    database::jobs::finished_time.eq(Some(current_time)),
+                ))
+                .execute(c)
+        }
+    }
+}
+
+pub fn retry_dead_job(c: &PgConnection, job_id: i32) -> QueryResult<usize> {
+    diesel::update(
+        database::jobs::table
+            .find(job_id)
+            .filter(database::jobs::status.eq(JobStatusEnum::Dead)),
+    )
+    .set((
+        database::jobs::status.eq(JobStatusEnum::Pending),
+        database::jobs::attempts.eq(0),
+        database::jobs::run_at.eq(Utc::now().naive_utc()),
+        database::jobs::finished_time.eq(None::<NaiveDateTime>),
+    ))
+    .execute(c)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PurgeFinishedJobs;
+
+#[rocket::async_trait]
+impl BackgroundJob for PurgeFinishedJobs {
+    const JOB_TYPE: &'static str = "job.purge_finished";
+
+    async fn run(self, context: &JobContext) -> Result<(), String> {
+        let expiration_time = Utc::now().naive_utc() - Duration::days(*JOB_RETENTION_DAYS);
+        let purged_count = context
+            .db
+            .run(move |c| {
+                diesel::delete(
+                    database::jobs::table
+                        .filter(database::jobs::status.eq(JobStatusEnum::Succeeded))
+                        .filter(database::jobs::finished_time.lt(expiration_time)),
+                )
+                .execute(c)
+            })
+            .await
+            .map_err(|error| error.to_string())?;
+        info!("已清理{}个已完成的任务", purged_count);
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::jobs::{
+    new_recurring_job, BackgroundJob, CheckReportStorage, JobContext, PurgeFinishedJobs,
+    REPORT_STORAGE_CHECK_SECONDS,
+};
+use crate::models::NewJob;
+use crate::notifications::{
+    DeliverDueNotifications, DeliverDuePartnerWebhooks, SendStageReminders,
+    NOTIFICATION_POLL_SECONDS, PARTNER_WEBHOOK_POLL_SECONDS, REMINDER_CHECK_SECONDS,
+};
+
+#[rocket::async_trait]
+pub trait JobRunner: Send + Sync {
+    async fn run(&self, payload: &str, context: &JobContext) -> Result<(), String>;
+}
+
+struct TypedJobRunner<J>(PhantomData<fn() -> J>);
+
+#[rocket::async_trait]
+impl<J: BackgroundJob> JobRunner for TypedJobRunner<J> {
+    async fn run(&self, payload: &str, context: &JobContext) -> Result<(), String> {
+        let job: J = serde_json::from_str(payload)
+            .map_err(|error| format!("任务参数解析失败：{}", error))?;
+        job.run(context).await
+    }
+}
+
+pub struct JobRegistry {
+    runners: HashMap<&'static str, Arc<dyn JobRunner>>,
+    recurring_jobs: Vec<NewJob>,
+}
+
+impl JobRegistry {
+    pub fn load() -> Self {
+        Self {
+            runners: HashMap::new(),
+            recurring_jobs: Vec::new(),
+        }
+        .recurring(DeliverDueNotifications, *NOTIFICATION_POLL_SECONDS as i32)
+        .recurring(
+            DeliverDuePartnerWebhooks,
+            *PARTNER_WEBHOOK_POLL_SECONDS as i32,
+        )
+        .recurring(SendStageReminders, *REMINDER_CHECK_SECONDS as i32)
+        .recurring(PurgeFinishedJobs, 86400)
+        .recurring(
+            CheckReportStorage::load(),
+            *REPORT_STORAGE_CHECK_SECONDS as i32,
+        )
+    }
+
+    pub fn register<J: BackgroundJob>(mut self) -> Self {
+        self.runners
+            .insert(J::JOB_TYPE, Arc::new(TypedJobRunner::<J>(PhantomData)));
+        self
+    }
+
+    pub fn recurring<J: BackgroundJob>(mut self, job: J, interval_seconds: i32) -> Self {
+        self.recurring_jobs
+            .push(new_recurring_job(&job, interval_seconds).expect("定时任务参数无法序列化"));
+        self.register::<J>()
+    }
+
+    pub fn runner(&self, job_type: &str) -> Option<Arc<dyn JobRunner>> {
+        self.runners.get(job_type).cloned()
+    }
+
+    pub fn recurring_jobs(&self) -> Vec<NewJob> {
+        self.recurring_jobs.clone()
+    }
+}
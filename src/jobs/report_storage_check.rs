@@ -0,0 +1,161 @@
+use chrono::prelude::*;
+use chrono::Duration;
+
+use data_encoding::HEXLOWER;
+
+use diesel::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use sha2::{Digest, Sha256};
+
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::auxiliary::{read_config_or, GenericError, ReportStorage};
+use crate::database::{self, MainDatabaseConnection};
+use crate::jobs::{BackgroundJob, JobContext};
+use crate::models::{ReportFileIssue, ReportStorageCheck};
+
+lazy_static! {
+    pub static ref REPORT_STORAGE_CHECK_SECONDS: u64 =
+        read_config_or("REPORT_STORAGE_CHECK_SECONDS", 86400);
+    static ref REPORT_STORAGE_CHECK_DELETE_ORPHANS: bool =
+        read_config_or("REPORT_STORAGE_CHECK_DELETE_ORPHANS", false);
+    static ref REPORT_STORAGE_CHECK_VERIFY_CHECKSUMS: bool =
+        read_config_or("REPORT_STORAGE_CHECK_VERIFY_CHECKSUMS", true);
+    // A file is stored before its row is inserted, so a fresh file without a row is not an orphan yet
+    static ref REPORT_ORPHAN_MIN_AGE_SECONDS: i64 =
+        read_config_or("REPORT_ORPHAN_MIN_AGE_SECONDS", 3600);
+}
+
+/// Reconciles the report storage against the reports table. Rows of retracted and superseded
+/// reports still refer to their files, so only files no row refers to count as orphans.
+pub async fn check_report_storage(
+    db: &MainDatabaseConnection,
+    storage: &dyn ReportStorage,
+    delete_orphans: bool,
+    verify_checksums: bool,
+) -> Result<ReportStorageCheck, GenericError> {
+    let checked_time = Utc::now().naive_utc();
+    let stored_files = storage.list().await?;
+    let referenced_files: Vec<(Uuid, Option<String>, Option<String>)> = db
+        .run(|c| {
+            database::reports::table
+                .filter(database::reports::filename.is_not_null())
+                .select((
+                    database::reports::id,
+                    database::reports::filename,
+                    database::reports::sha256,
+                ))
+                .get_results(c)
+        })
+        .await?;
+    let referenced_keys: HashSet<&str> = referenced_files
+        .iter()
+        .filter_map(|(_, filename, _)| filename.as_deref())
+        .collect();
+    let stored_keys: HashSet<&str> = stored_files.iter().map(|file| file.key.as_str()).collect();
+
+    let orphan_expiration = checked_time - Duration::seconds(*REPORT_ORPHAN_MIN_AGE_SECONDS);
+    let orphan_files: Vec<String> = stored_files
+        .iter()
+        .filter(|file| !referenced_keys.contains(file.key.as_str()))
+        .filter(|file| {
+            !matches!(file.modified_time, Some(modified_time) if modified_time >= orphan_expiration)
+        })
+        .map(|file| file.key.to_owned())
+        .collect();
+    let mut deleted_orphan_files = Vec::new();
+    if delete_orphans {
+        for key in &orphan_files {
+            match storage.delete(key).await {
+                Ok(()) => deleted_orphan_files.push(key.to_owned()),
+                Err(error) => warn!("删除孤立报告文件{}时出错：{:?}", key, error),
+            }
+        }
+    }
+
+    let mut missing_files = Vec::new();
+    let mut checksum_mismatches = Vec::new();
+    for (report_id, filename, sha256) in &referenced_files {
+        let filename = filename.to_owned().unwrap_or_default();
+        let issue = ReportFileIssue {
+            report_id: *report_id,
+            filename: filename.to_owned(),
+        };
+        if !stored_keys.contains(filename.as_str()) {
+            missing_files.push(issue);
+            continue;
+        }
+        let sha256 = match sha256 {
+            Some(sha256) if verify_checksums => sha256,
+            _ => continue,
+        };
+        match storage.get(&filename).await? {
+            Some(content) => {
+                if HEXLOWER.encode(&Sha256::digest(&content)) != *sha256 {
+                    checksum_mismatches.push(issue);
+                }
+            }
+            None => missing_files.push(issue),
+        }
+    }
+
+    Ok(ReportStorageCheck {
+        checked_time,
+        stored_file_count: stored_files.len(),
+        referenced_file_count: referenced_keys.len(),
+        orphan_files,
+        deleted_orphan_files,
+        missing_files,
+        checksum_mismatches,
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CheckReportStorage {
+    pub delete_orphans: bool,
+    pub verify_checksums: bool,
+}
+
+impl CheckReportStorage {
+    pub fn load() -> Self {
+        Self {
+            delete_orphans: *REPORT_STORAGE_CHECK_DELETE_ORPHANS,
+            verify_checksums: *REPORT_STORAGE_CHECK_VERIFY_CHECKSUMS,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl BackgroundJob for CheckReportStorage {
+    const JOB_TYPE: &'static str = "report.check_storage";
+
+    async fn run(self, context: &JobContext) -> Result<(), String> {
+        let check = check_report_storage(
+            &context.db,
+            context.report_storage.as_ref(),
+            self.delete_orphans,
+            self.verify_checksums,
+        )
+        .await
+        .map_err(|error| format!("{:?}", error))?;
+        info!(
+            "报告存储检查完成：共{}个文件，孤立文件{}个（已删除{}个），缺失文件{}个，校验和不符{}个",
+            check.stored_file_count,
+            check.orphan_files.len(),
+            check.deleted_orphan_files.len(),
+            check.missing_files.len(),
+            check.checksum_mismatches.len()
+        );
+        for issue in &check.missing_files {
+            warn!("报告{}的文件{}不存在", issue.report_id, issue.filename);
+        }
+        for issue in &check.checksum_mismatches {
+            warn!("报告{}的文件{}校验和不符", issue.report_id, issue.filename);
+        }
+        Ok(())
+    }
+}
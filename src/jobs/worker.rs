@@ -0,0 +1,118 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::tokio::time::sleep;
+use rocket::{Orbit, Rocket};
+
+use std::sync::Arc;
+
+use crate::auxiliary::{
+    read_config_or, GenericError, ReportStorage, ReportStorageState, SmsSenderState,
+    WechatAccessTokenState,
+};
+use crate::database::MainDatabaseConnection;
+use crate::jobs::{claim_due_jobs, record_job_result, register_recurring_jobs, JobRegistry};
+use crate::notifications::NotifierSet;
+
+lazy_static! {
+    static ref JOB_POLL_SECONDS: u64 = read_config_or("JOB_POLL_SECONDS", 5);
+}
+
+/// What a job handler gets to work with.
+pub struct JobContext {
+    pub db: MainDatabaseConnection,
+    pub notifier_set: NotifierSet,
+    pub report_storage: Arc<dyn ReportStorage>,
+}
+
+async fn run_due_jobs(
+    registry: &JobRegistry,
+    context: &Arc<JobContext>,
+) -> Result<usize, GenericError> {
+    let due_jobs = context.db.run(|c| claim_due_jobs(c)).await?;
+    let job_count = due_jobs.len();
+    for job in due_jobs {
+        let result = match registry.runner(&job.job_type) {
+            Some(runner) => {
+                let payload = job.payload.to_owned();
+                let task_context = context.clone();
+                // Running the handler in its own task turns a panic into an ordinary failure
+                rocket::tokio::spawn(async move { runner.run(&payload, &task_context).await })
+                    .await
+                    .unwrap_or_else(|error| Err(format!("任务执行异常：{}", error)))
+            }
+            None => Err(format!("未知的任务类型{}", job.job_type)),
+        };
+        if let Err(error) = &result {
+            info!("任务{}（{}）执行失败：{}", job.id, job.job_type, error);
+        }
+        context
+            .db
+            .run(move |c| record_job_result(c, &job, result))
+            .await?;
+    }
+    Ok(job_count)
+}
+
+pub struct JobWorker;
+
+#[rocket::async_trait]
+impl Fairing for JobWorker {
+    fn info(&self) -> Info {
+        Info {
+            name: "Background job worker",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let notifier_set = match (
+            rocket.state::<WechatAccessTokenState>(),
+            rocket.state::<SmsSenderState>(),
+        ) {
+            (Some(access_token_state), Some(sms_sender_state)) => {
+                NotifierSet::load(access_token_state.clone(), sms_sender_state.sender.clone())
+            }
+            _ => {
+                error!("WechatAccessTokenState或SmsSenderState未加载");
+                return;
+            }
+        };
+        let report_storage = match rocket.state::<ReportStorageState>() {
+            Some(report_storage_state) => report_storage_state.storage.clone(),
+            None => {
+                error!("ReportStorageState未加载");
+                return;
+            }
+        };
+        // The worker keeps one pooled connection for its whole lifetime
+        let db = match MainDatabaseConnection::get_one(rocket).await {
+            Some(db) => db,
+            None => {
+                error!("后台任务无法获取数据库连接");
+                return;
+            }
+        };
+        let registry = JobRegistry::load();
+        let recurring_jobs = registry.recurring_jobs();
+        if let Err(error) = db
+            .run(move |c| register_recurring_jobs(c, &recurring_jobs))
+            .await
+        {
+            error!("注册定时任务时出错：{:?}", error);
+        }
+        let context = Arc::new(JobContext {
+            db,
+            notifier_set,
+            report_storage,
+        });
+        rocket::tokio::spawn(async move {
+            loop {
+                match run_due_jobs(&registry, &context).await {
+                    Ok(job_count) if job_count > 0 => continue,
+                    Ok(_) => (),
+                    Err(error) => error!("执行后台任务时出错：{:?}", error),
+                }
+                sleep(std::time::Duration::from_secs(*JOB_POLL_SECONDS)).await;
+            }
+        });
+    }
+}
@@ -1,7 +1,9 @@
 mod auth;
 mod auxiliary;
 mod database;
+mod jobs;
 mod models;
+mod notifications;
 mod routes;
 
 #[macro_use]
@@ -22,10 +24,16 @@ use chrono;
 
 use log;
 
+use rocket::data::{Limits, ToByteUnit};
+
 use std::env;
 
-use crate::auxiliary::{ProductBarcodeGeneratorState, WechatAccessToken, CORS};
+use crate::auxiliary::{
+    ProductBarcodeGeneratorState, RateLimiterState, ReportStorageState, SmsSenderState,
+    WechatAccessTokenRefresher, WechatAccessTokenState, CORS, REPORT_MAX_SIZE,
+};
 use crate::database::MainDatabaseConnection;
+use crate::jobs::JobWorker;
 use crate::routes::*;
 
 #[rocket::main]
@@ -49,19 +57,49 @@ async fn main() {
         .apply()
         .expect("log引擎初始化错误");
 
-    let rocket_instance = rocket::build()
+    // Multipart report uploads need at least the report size limit, other limits and larger
+    // ones set in Rocket.toml or ROCKET_LIMITS are kept
+    let figment = rocket::Config::figment();
+    let limits: Limits = figment
+        .extract_inner(rocket::Config::LIMITS)
+        .expect("limits配置格式错误");
+    let min_file_limit = *REPORT_MAX_SIZE;
+    let min_data_form_limit = *REPORT_MAX_SIZE + 1.mebibytes();
+    let file_limit = limits
+        .get("file")
+        .map_or(min_file_limit, |limit| limit.max(min_file_limit));
+    let data_form_limit = limits
+        .get("data-form")
+        .map_or(min_data_form_limit, |limit| limit.max(min_data_form_limit));
+    let figment = figment.merge((
+        rocket::Config::LIMITS,
+        limits
+            .limit("file", file_limit)
+            .limit("data-form", data_form_limit),
+    ));
+    let rocket_instance = rocket::custom(figment)
         .mount("/api/user", user_routes())
+        .mount("/api/user/totp", totp_routes())
         .mount("/api/product", product_routes())
         .mount("/api/profile", profile_routes())
         .mount("/api/report", report_routes())
-        .mount("/api/wechat,wechat_validation", wechat_validation_routes())
+        .mount("/api/report_template", report_template_routes())
+        .mount("/api/kit_type", kit_type_routes())
+        .mount("/api/notification", notification_routes())
+        .mount("/api/webhook", webhook_routes())
+        .mount("/api/job", job_routes())
+        .mount("/api/wechat", wechat_validation_routes())
         .attach(MainDatabaseConnection::fairing())
         .register("/api", api_error_catchers())
         //TODO:CORS
         .attach(CORS)
-        //TODO:Access Token
-        //.manage(WechatAccessToken::new())
-        .manage(ProductBarcodeGeneratorState::load());
+        .attach(WechatAccessTokenRefresher)
+        .attach(JobWorker)
+        .manage(WechatAccessTokenState::new())
+        .manage(ProductBarcodeGeneratorState::load())
+        .manage(RateLimiterState::load())
+        .manage(SmsSenderState::load())
+        .manage(ReportStorageState::load());
     rocket_instance.launch().await;
 
     //TODO: logging
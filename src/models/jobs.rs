@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+
+use rocket::request::FromParam;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::auxiliary::GenericError;
+use crate::database::*;
+
+#[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Copy)]
+#[DieselType = "Job_status"]
+#[DbValueStyle = "PascalCase"]
+pub enum JobStatusEnum {
+    Pending,
+    Running,
+    Succeeded,
+    Dead,
+}
+
+impl<'a> FromParam<'a> for JobStatusEnum {
+    type Error = GenericError;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param.to_lowercase().as_ref() {
+            "pending" => Ok(Self::Pending),
+            "running" => Ok(Self::Running),
+            "succeeded" => Ok(Self::Succeeded),
+            "dead" => Ok(Self::Dead),
+            _ => Err(GenericError::InvalidInputError),
+        }
+    }
+}
+
+#[derive(Queryable, Deserialize, Serialize, Clone, Debug)]
+pub struct Job {
+    pub id: i32,
+    pub job_type: String,
+    pub payload: String,
+    pub status: JobStatusEnum,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    pub unique_key: Option<String>,
+    pub interval_seconds: Option<i32>,
+    pub created_time: NaiveDateTime,
+    pub run_at: NaiveDateTime,
+    pub finished_time: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Clone)]
+#[table_name = "jobs"]
+pub struct NewJob {
+    pub job_type: String,
+    pub payload: String,
+    pub max_attempts: i32,
+    pub unique_key: Option<String>,
+    pub interval_seconds: Option<i32>,
+    pub created_time: NaiveDateTime,
+    pub run_at: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ClientRetryJobData {
+    pub job_id: i32,
+}
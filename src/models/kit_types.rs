@@ -0,0 +1,57 @@
+use serde::{self, Deserialize, Serialize};
+
+use crate::database::*;
+
+#[derive(Queryable, Serialize, Deserialize, Clone, Debug)]
+pub struct TestType {
+    pub id: i32,
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Insertable, Deserialize)]
+#[table_name = "test_types"]
+pub struct NewTestType {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct KitType {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "kit_type_tests"]
+pub struct NewKitTypeTest {
+    pub kit_type_id: i32,
+    pub test_type_id: i32,
+}
+
+/// A kit type with the tests that must all have a released report before a kit is finished.
+#[derive(Serialize)]
+pub struct KitTypeDetail {
+    pub id: i32,
+    pub name: String,
+    pub test_types: Vec<TestType>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientKitTypeData {
+    pub name: String,
+    pub test_type_ids: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientUpdateKitTypeData {
+    pub kit_type_id: i32,
+    #[serde(flatten)]
+    pub kit_type: ClientKitTypeData,
+}
+
+#[derive(Deserialize)]
+pub struct ClientSetProductKitTypeData {
+    pub product_barcode: String,
+    pub kit_type_id: Option<i32>,
+}
@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::database::*;
+
+#[derive(Queryable, Deserialize, Serialize)]
+pub struct LockoutEvent {
+    pub id: i32,
+    pub user_id: i32,
+    pub client_ip: Option<String>,
+    pub event_time: NaiveDateTime,
+    pub locked_until: NaiveDateTime,
+    pub cleared_by: Option<i32>,
+    pub cleared_time: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "lockout_events"]
+pub struct NewLockoutEvent {
+    pub user_id: i32,
+    pub client_ip: Option<String>,
+    pub event_time: NaiveDateTime,
+    pub locked_until: NaiveDateTime,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct LockedUserDigest {
+    pub user_id: i32,
+    pub username: Option<String>,
+    pub lockout_count: i32,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientClearLockData {
+    pub user_id: i32,
+}
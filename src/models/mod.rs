@@ -1,9 +1,29 @@
+mod jobs;
+mod kit_types;
+mod lockouts;
+mod notifications;
 mod products;
 mod profiles;
+mod report_templates;
 mod reports;
+mod sms_codes;
+mod totp;
 mod users;
+mod webhooks;
+mod wechat_identities;
+mod wechat_messages;
 
+pub use jobs::*;
+pub use kit_types::*;
+pub use lockouts::*;
+pub use notifications::*;
 pub use products::*;
 pub use profiles::*;
+pub use report_templates::*;
 pub use reports::*;
+pub use sms_codes::*;
+pub use totp::*;
 pub use users::*;
+pub use webhooks::*;
+pub use wechat_identities::*;
+pub use wechat_messages::*;
@@ -0,0 +1,146 @@
+use chrono::NaiveDateTime;
+
+use rocket::request::FromParam;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::auxiliary::GenericError;
+use crate::database::*;
+use crate::models::StageEnum;
+
+#[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Copy)]
+#[DieselType = "Notification_channel"]
+#[DbValueStyle = "PascalCase"]
+pub enum NotificationChannelEnum {
+    Wechat,
+    Email,
+    Sms,
+    Webhook,
+}
+
+#[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Copy)]
+#[DieselType = "Notification_status"]
+#[DbValueStyle = "PascalCase"]
+pub enum NotificationStatusEnum {
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl<'a> FromParam<'a> for NotificationStatusEnum {
+    type Error = GenericError;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param.to_lowercase().as_ref() {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "failed" => Ok(Self::Failed),
+            _ => Err(GenericError::InvalidInputError),
+        }
+    }
+}
+
+#[derive(Queryable, Deserialize, Serialize, Clone, Debug)]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub product_id: i32,
+    pub channel: NotificationChannelEnum,
+    pub recipient: String,
+    pub event_stage: StageEnum,
+    pub payload: String,
+    pub status: NotificationStatusEnum,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_time: NaiveDateTime,
+    pub next_attempt_time: NaiveDateTime,
+    pub sent_time: Option<NaiveDateTime>,
+    pub reminder_id: Option<i32>,
+}
+
+#[derive(Insertable, Clone)]
+#[table_name = "notifications"]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub product_id: i32,
+    pub channel: NotificationChannelEnum,
+    pub recipient: String,
+    pub event_stage: StageEnum,
+    pub payload: String,
+    pub created_time: NaiveDateTime,
+    pub next_attempt_time: NaiveDateTime,
+    pub reminder_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientRetryNotificationData {
+    pub notification_id: i32,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct ProductReminder {
+    pub id: i32,
+    pub product_id: i32,
+    pub stage: StageEnum,
+    pub created_time: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "product_reminders"]
+pub struct NewProductReminder {
+    pub product_id: i32,
+    pub stage: StageEnum,
+    pub created_time: NaiveDateTime,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct NotificationPreference {
+    pub user_id: i32,
+    pub wechat_enabled: bool,
+    pub email_enabled: bool,
+    pub email: Option<String>,
+    pub sms_enabled: bool,
+    pub webhook_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub updated_time: NaiveDateTime,
+}
+
+impl NotificationPreference {
+    // Users who never saved their preferences keep getting WeChat notifications only
+    pub fn default_for(user_id: i32, updated_time: NaiveDateTime) -> Self {
+        Self {
+            user_id,
+            wechat_enabled: true,
+            email_enabled: false,
+            email: None,
+            sms_enabled: false,
+            webhook_enabled: false,
+            webhook_url: None,
+            updated_time,
+        }
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "notification_preferences"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewNotificationPreference {
+    pub user_id: i32,
+    pub wechat_enabled: bool,
+    pub email_enabled: bool,
+    pub email: Option<String>,
+    pub sms_enabled: bool,
+    pub webhook_enabled: bool,
+    pub webhook_url: Option<String>,
+    pub updated_time: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ClientNotificationPreferenceData {
+    pub wechat_enabled: bool,
+    pub email_enabled: bool,
+    pub email: Option<String>,
+    pub sms_enabled: bool,
+    pub webhook_enabled: bool,
+    pub webhook_url: Option<String>,
+}
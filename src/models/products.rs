@@ -4,8 +4,6 @@ use rocket::request::FromParam;
 
 use serde::{self, Deserialize, Serialize};
 
-use uuid::Uuid;
-
 use crate::{auxiliary::GenericError, database::*};
 
 #[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Copy)]
@@ -40,7 +38,7 @@ pub struct Product {
     pub profile_id: Option<i32>,
     pub init_time: NaiveDateTime,
     pub current_stage: StageEnum,
-    pub report_id: Option<Uuid>,
+    pub kit_type_id: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Queryable, Insertable, Clone, Debug)]
@@ -58,7 +56,7 @@ pub struct ProductDigest {
     pub product_barcode: String,
     pub init_time: NaiveDateTime,
     pub current_stage: StageEnum,
-    pub report_id: Option<Uuid>,
+    pub kit_type_id: Option<i32>,
 }
 
 #[derive(AsChangeset)]
@@ -10,14 +10,14 @@ pub struct Profile {
     pub user_id: i32,
     pub submit_time: NaiveDateTime,
 
-    name: String,
-    id_card_number: String,
-    birth_date: NaiveDateTime,
+    pub name: String,
+    pub id_card_number: String,
+    pub birth_date: NaiveDateTime,
     profession: String,
     address: String,
     phone: String,
 
-    sample_time: Option<NaiveDateTime>,
+    pub sample_time: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
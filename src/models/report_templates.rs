@@ -0,0 +1,67 @@
+use chrono::NaiveDateTime;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::database::*;
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct ReportTemplate {
+    pub id: i32,
+    pub name: String,
+    pub title: String,
+    pub header: String,
+    pub body: String,
+    pub footer: String,
+    pub is_default: bool,
+    pub updated_by: Option<i32>,
+    pub updated_time: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "report_templates"]
+pub struct NewReportTemplate {
+    pub name: String,
+    pub title: String,
+    pub header: String,
+    pub body: String,
+    pub footer: String,
+    pub is_default: bool,
+    pub updated_by: Option<i32>,
+    pub updated_time: NaiveDateTime,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "report_templates"]
+pub struct UpdateReportTemplate {
+    pub name: String,
+    pub title: String,
+    pub header: String,
+    pub body: String,
+    pub footer: String,
+    pub is_default: bool,
+    pub updated_by: Option<i32>,
+    pub updated_time: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ClientReportTemplateData {
+    pub name: String,
+    pub title: String,
+    pub header: String,
+    pub body: String,
+    pub footer: String,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ClientUpdateReportTemplateData {
+    pub template_id: i32,
+    #[serde(flatten)]
+    pub template: ClientReportTemplateData,
+}
+
+#[derive(Deserialize)]
+pub struct ClientReportTemplateIdData {
+    pub template_id: i32,
+}
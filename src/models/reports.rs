@@ -1,10 +1,38 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
+
+use rocket::data::Capped;
+use rocket::fs::TempFile;
+use rocket::request::FromParam;
 
 use serde::{self, Deserialize, Serialize};
 
 use uuid::Uuid;
 
+use crate::auxiliary::GenericError;
 use crate::database::*;
+use crate::models::TestType;
+
+#[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Copy)]
+#[DieselType = "Report_review_status"]
+#[DbValueStyle = "PascalCase"]
+pub enum ReportReviewStatusEnum {
+    PendingReview,
+    Approved,
+    Rejected,
+}
+
+impl<'a> FromParam<'a> for ReportReviewStatusEnum {
+    type Error = GenericError;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param.to_lowercase().as_ref() {
+            "pendingreview" => Ok(Self::PendingReview),
+            "approved" => Ok(Self::Approved),
+            "rejected" => Ok(Self::Rejected),
+            _ => Err(GenericError::InvalidInputError),
+        }
+    }
+}
 
 #[derive(Queryable, Deserialize, Serialize)]
 pub struct Report {
@@ -13,20 +41,223 @@ pub struct Report {
     pub filename: Option<String>,
     pub download_url: String,
     pub upload_time: NaiveDateTime,
+    pub sha256: Option<String>,
+    pub file_size: Option<i64>,
+    pub page_count: Option<i32>,
+    pub original_filename: Option<String>,
+    pub result_summary: Option<String>,
+    pub technician_comments: Option<String>,
+    pub lab_accession_number: Option<String>,
+    pub product_id: Option<i32>,
+    pub version: i32,
+    pub amendment_reason: Option<String>,
+    pub retracted_time: Option<NaiveDateTime>,
+    pub retracted_by: Option<i32>,
+    pub retraction_reason: Option<String>,
+    pub review_status: ReportReviewStatusEnum,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_time: Option<NaiveDateTime>,
+    pub review_comments: Option<String>,
+    pub template_id: Option<i32>,
+    pub result_items: Option<String>,
+    pub verification_code: String,
+    pub result_category: Option<String>,
+    #[serde(skip_serializing)]
+    pub signature: Option<Vec<u8>>,
+    pub test_type_id: Option<i32>,
+    pub is_current: bool,
+    pub amended: bool,
 }
 
 #[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
 #[table_name = "reports"]
 pub struct NewReport {
+    pub id: Uuid,
     pub uploader_id: i32,
     pub filename: Option<String>,
     pub download_url: String,
     pub upload_time: NaiveDateTime,
+    pub sha256: Option<String>,
+    pub file_size: Option<i64>,
+    pub page_count: Option<i32>,
+    pub original_filename: Option<String>,
+    pub result_summary: Option<String>,
+    pub technician_comments: Option<String>,
+    pub lab_accession_number: Option<String>,
+    pub product_id: Option<i32>,
+    pub version: i32,
+    pub amendment_reason: Option<String>,
+    pub review_status: ReportReviewStatusEnum,
+    pub template_id: Option<i32>,
+    pub result_items: Option<String>,
+    pub verification_code: String,
+    pub result_category: Option<String>,
+    pub signature: Option<Vec<u8>>,
+    pub test_type_id: Option<i32>,
+    pub is_current: bool,
+    pub amended: bool,
+}
+
+/// What a customer sees of their report, `amended` is set when it replaces a released version.
+#[derive(Serialize)]
+pub struct ReportSummary {
+    pub id: Uuid,
+    pub download_url: String,
+    pub upload_time: NaiveDateTime,
+    pub version: i32,
+    pub amended: bool,
+    pub amendment_reason: Option<String>,
+    pub retracted: bool,
+    pub verification_code: String,
+    pub signed: bool,
+    pub test_type_id: Option<i32>,
+}
+
+impl From<Report> for ReportSummary {
+    fn from(report: Report) -> Self {
+        Self {
+            id: report.id,
+            download_url: report.download_url,
+            upload_time: report.upload_time,
+            version: report.version,
+            amended: report.amended,
+            amendment_reason: report.amendment_reason,
+            retracted: report.retracted_time.is_some(),
+            verification_code: report.verification_code,
+            signed: report.signature.is_some(),
+            test_type_id: report.test_type_id,
+        }
+    }
+}
+
+/// Query string filters of the staff listing, times are millisecond timestamps like elsewhere.
+#[derive(FromForm, Default)]
+pub struct ReportListFilter {
+    pub uploader_id: Option<i32>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub stage: Option<String>,
+    pub review_status: Option<String>,
+}
+
+/// A current report of one of the user's products, one for each test of the kit.
+#[derive(Serialize)]
+pub struct UserReport {
+    #[serde(flatten)]
+    pub report: ReportSummary,
+    pub product_barcode: String,
+    pub sample_time: Option<NaiveDateTime>,
+    pub test_type: Option<TestType>,
 }
 
 #[derive(Deserialize)]
-pub struct ClientRemoveReportData {
+pub struct ClientReviewReportData {
     pub report_id: Uuid,
+    pub approved: bool,
+    pub comments: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientRetractReportData {
+    pub report_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Default)]
+pub struct ReportMetadata {
+    pub original_filename: Option<String>,
+    pub result_summary: Option<String>,
+    pub result_category: Option<String>,
+    pub technician_comments: Option<String>,
+    pub lab_accession_number: Option<String>,
+    pub amendment_reason: Option<String>,
+    pub test_type: Option<String>,
+    pub template_id: Option<i32>,
+    pub result_items: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct ReportUploadForm<'r> {
+    pub file: Capped<TempFile<'r>>,
+    pub original_filename: Option<String>,
+    pub result_summary: Option<String>,
+    pub result_category: Option<String>,
+    pub technician_comments: Option<String>,
+    pub lab_accession_number: Option<String>,
+    pub amendment_reason: Option<String>,
+    pub test_type: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct ReportSignatureCheckForm<'r> {
+    pub file: Capped<TempFile<'r>>,
+    // The detached .p7s handed out by the signature endpoint, in DER
+    pub signature: Option<Capped<TempFile<'r>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReportResultItem {
+    pub item: String,
+    pub result: String,
+    pub unit: Option<String>,
+    pub reference_range: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientGenerateReportData {
+    pub product_barcode: String,
+    pub template_id: Option<i32>,
+    pub results: Vec<ReportResultItem>,
+    pub result_summary: Option<String>,
+    pub result_category: Option<String>,
+    pub technician_comments: Option<String>,
+    pub lab_accession_number: Option<String>,
+    pub amendment_reason: Option<String>,
+    pub test_type: Option<String>,
+}
+
+/// All a third party learns from a verification code, the name is masked.
+#[derive(Serialize)]
+pub struct ReportVerification {
+    pub issue_date: NaiveDate,
+    pub result_category: Option<String>,
+    pub masked_name: Option<String>,
+    pub amended: bool,
+    pub superseded: bool,
+    pub retracted: bool,
+}
+
+/// A submitted file only checks out if it is byte for byte a report we signed.
+#[derive(Serialize)]
+pub struct ReportSignatureCheck {
+    pub valid: bool,
+    pub verification_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReportFileIssue {
+    pub report_id: Uuid,
+    pub filename: String,
+}
+
+/// Summary of reconciling the report storage against the reports table.
+#[derive(Serialize)]
+pub struct ReportStorageCheck {
+    pub checked_time: NaiveDateTime,
+    pub stored_file_count: usize,
+    pub referenced_file_count: usize,
+    pub orphan_files: Vec<String>,
+    pub deleted_orphan_files: Vec<String>,
+    pub missing_files: Vec<ReportFileIssue>,
+    pub checksum_mismatches: Vec<ReportFileIssue>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientCheckReportStorageData {
+    #[serde(default)]
+    pub delete_orphans: bool,
+    #[serde(default)]
+    pub verify_checksums: bool,
 }
 
 #[derive(Deserialize)]
@@ -34,4 +265,38 @@ pub struct PublishReportData {
     pub product_barcode: String,
     pub filename: Option<String>,
     pub download_url: Option<String>,
+    pub amendment_reason: Option<String>,
+    pub test_type: Option<String>,
+}
+
+#[derive(Queryable, Serialize)]
+pub struct ReportDownload {
+    pub id: i32,
+    pub report_id: Uuid,
+    pub user_id: Option<i32>,
+    pub shared_by: Option<i32>,
+    pub client_ip: Option<String>,
+    pub download_time: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "report_downloads"]
+pub struct NewReportDownload {
+    pub report_id: Uuid,
+    pub user_id: Option<i32>,
+    pub shared_by: Option<i32>,
+    pub client_ip: Option<String>,
+    pub download_time: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ClientShareReportData {
+    pub report_id: Uuid,
+    pub expires_in_seconds: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ReportShareLink {
+    pub url: String,
+    pub expiration_time: NaiveDateTime,
 }
@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::database::*;
+
+#[derive(Queryable, Deserialize, Serialize)]
+pub struct SmsCode {
+    pub id: i32,
+    pub phone_number: String,
+    pub code_hashed: String,
+    pub created_time: NaiveDateTime,
+    pub expiration_time: NaiveDateTime,
+    pub attempts: i32,
+    pub consumed_time: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "sms_codes"]
+pub struct NewSmsCode {
+    pub phone_number: String,
+    pub code_hashed: String,
+    pub created_time: NaiveDateTime,
+    pub expiration_time: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ClientRequestSmsCodeData {
+    pub phone_number: String,
+}
+
+#[derive(Deserialize)]
+pub struct ClientSmsLoginData {
+    pub phone_number: String,
+    pub code: String,
+}
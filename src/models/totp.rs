@@ -0,0 +1,46 @@
+use chrono::NaiveDateTime;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::database::*;
+use crate::models::RoleEnum;
+
+#[derive(Queryable, Deserialize, Serialize)]
+pub struct TotpPolicy {
+    pub user_role: RoleEnum,
+    pub totp_required: bool,
+    pub updated_by: Option<i32>,
+    pub updated_time: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[table_name = "totp_recovery_codes"]
+pub struct NewTotpRecoveryCode {
+    pub user_id: i32,
+    pub code_hashed: String,
+    pub created_time: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct TotpEnrollmentDigest {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub qrcode_svg: String,
+}
+
+#[derive(Deserialize)]
+pub struct ClientTotpCodeData {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct ClientTotpVerifyData {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ClientSetTotpPolicyData {
+    pub user_role: RoleEnum,
+    pub totp_required: bool,
+}
@@ -7,7 +7,7 @@ use serde::{self, Deserialize, Serialize};
 use crate::auxiliary::GenericError;
 use crate::database::*;
 
-#[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Copy)]
+#[derive(DbEnum, Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
 #[DieselType = "Role"]
 #[DbValueStyle = "PascalCase"]
 pub enum RoleEnum {
@@ -36,8 +36,17 @@ pub struct User {
     pub wechat_id: Option<String>,
     pub user_role: RoleEnum,
     pub password_hashed: Option<String>,
-    pub phone_number: Option<i32>,
+    pub phone_number: Option<String>,
     pub sign_up_time: NaiveDateTime,
+    pub failed_login_count: i32,
+    pub lockout_count: i32,
+    pub locked_until: Option<NaiveDateTime>,
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    #[serde(skip_serializing)]
+    pub totp_last_used_step: Option<i64>,
+    pub report_reviewer: bool,
 }
 
 #[derive(Serialize, Deserialize, Queryable, Insertable, Clone, Debug)]
@@ -47,7 +56,7 @@ pub struct NewUserData {
     pub wechat_id: Option<String>,
     pub user_role: RoleEnum,
     pub password_hashed: Option<String>,
-    pub phone_number: Option<i32>,
+    pub phone_number: Option<String>,
     pub sign_up_time: NaiveDateTime,
 }
 
@@ -55,6 +64,7 @@ pub struct NewUserData {
 pub struct UserLoggedInDigest {
     pub username: Option<String>,
     pub user_role: RoleEnum,
+    pub totp_pending: bool,
 }
 
 #[derive(Deserialize)]
@@ -63,6 +73,18 @@ pub struct ClientUsernamePasswordData {
     pub password: String,
 }
 
+#[derive(Deserialize)]
+pub struct ClientChangePasswordData {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct ClientSetReportReviewerData {
+    pub user_id: i32,
+    pub report_reviewer: bool,
+}
+
 #[derive(Deserialize)]
 pub struct ClientChangeRoleData {
     pub user_id: i32,
@@ -74,6 +96,12 @@ pub struct ClientRemoveUserData {
     pub user_id: i32,
 }
 
+#[derive(Deserialize)]
+pub struct ClientMergeUsersData {
+    pub source_user_id: i32,
+    pub target_user_id: i32,
+}
+
 #[derive(Deserialize)]
 pub struct ClientWechatLoginData {
     pub code: String,
@@ -86,6 +114,15 @@ pub struct WechatOpenIdResponse {
     refresh_token: String,
     pub openid: String,
     scope: String,
+    #[serde(default)]
+    pub unionid: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WechatSessionResponse {
+    pub openid: String,
+    #[serde(default)]
+    pub unionid: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -97,7 +134,8 @@ pub struct WechatUserinfoResponse {
     country: String,
     headimgurl: String,
     privilege: Vec<String>,
-    unionid: String,
+    #[serde(default)]
+    pub unionid: Option<String>,
 }
 
 #[derive(Serialize)]
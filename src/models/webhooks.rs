@@ -0,0 +1,118 @@
+use chrono::NaiveDateTime;
+
+use serde::{self, Deserialize, Serialize};
+
+use uuid::Uuid;
+
+use crate::database::*;
+use crate::models::NotificationStatusEnum;
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct WebhookSubscription {
+    pub id: i32,
+    pub partner_name: String,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub barcode_prefix: Option<String>,
+    pub enabled: bool,
+    pub created_by: i32,
+    pub created_time: NaiveDateTime,
+    pub updated_time: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhook_subscriptions"]
+pub struct NewWebhookSubscription {
+    pub partner_name: String,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub barcode_prefix: Option<String>,
+    pub enabled: bool,
+    pub created_by: i32,
+    pub created_time: NaiveDateTime,
+    pub updated_time: NaiveDateTime,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "webhook_subscriptions"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct UpdateWebhookSubscription {
+    pub partner_name: String,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub barcode_prefix: Option<String>,
+    pub enabled: bool,
+    pub updated_time: NaiveDateTime,
+}
+
+/// Only returned when the subscription is created, afterwards the secret is never shown.
+#[derive(Serialize)]
+pub struct CreatedWebhookSubscription {
+    pub subscription: WebhookSubscription,
+    pub secret: String,
+}
+
+#[derive(Queryable, Serialize, Clone, Debug)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub subscription_id: i32,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+    pub status: NotificationStatusEnum,
+    pub attempts: i32,
+    pub response_code: Option<i32>,
+    pub response_body: Option<String>,
+    pub last_error: Option<String>,
+    pub created_time: NaiveDateTime,
+    pub next_attempt_time: NaiveDateTime,
+    pub delivered_time: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhook_deliveries"]
+pub struct NewWebhookDelivery {
+    pub subscription_id: i32,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+    pub created_time: NaiveDateTime,
+    pub next_attempt_time: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct ClientWebhookSubscriptionData {
+    pub partner_name: String,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub barcode_prefix: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct ClientUpdateWebhookSubscriptionData {
+    pub subscription_id: i32,
+    pub partner_name: String,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub barcode_prefix: Option<String>,
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ClientWebhookSubscriptionIdData {
+    pub subscription_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct ClientRedeliverWebhookData {
+    pub delivery_id: i32,
+}
@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+
+use serde::{self, Deserialize, Serialize};
+
+use crate::database::*;
+
+#[derive(Queryable, Deserialize, Serialize)]
+pub struct WechatIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub appid: String,
+    pub openid: String,
+    pub unionid: Option<String>,
+    pub created_time: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "wechat_identities"]
+pub struct NewWechatIdentity {
+    pub user_id: i32,
+    pub appid: String,
+    pub openid: String,
+    pub unionid: Option<String>,
+    pub created_time: NaiveDateTime,
+}
@@ -0,0 +1,55 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct WechatScanCodeInfo {
+    #[serde(rename = "ScanResult")]
+    pub scan_result: String,
+}
+
+#[derive(Deserialize)]
+pub struct WechatIncomingMessage {
+    #[serde(rename = "ToUserName")]
+    pub to_user_name: String,
+    #[serde(rename = "FromUserName")]
+    pub from_user_name: String,
+    #[serde(rename = "MsgType")]
+    pub msg_type: String,
+    #[serde(rename = "Event", default)]
+    pub event: Option<String>,
+    #[serde(rename = "EventKey", default)]
+    pub event_key: Option<String>,
+    #[serde(rename = "ScanCodeInfo", default)]
+    pub scan_code_info: Option<WechatScanCodeInfo>,
+    #[serde(rename = "Content", default)]
+    pub content: Option<String>,
+}
+
+pub struct WechatTextReply {
+    pub to_user_name: String,
+    pub from_user_name: String,
+    pub create_time: i64,
+    pub content: String,
+}
+
+// CDATA sections cannot contain "]]>", so it is split across two sections
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+impl WechatTextReply {
+    pub fn to_xml(&self) -> String {
+        format!(
+            "<xml>\
+            <ToUserName>{}</ToUserName>\
+            <FromUserName>{}</FromUserName>\
+            <CreateTime>{}</CreateTime>\
+            <MsgType><![CDATA[text]]></MsgType>\
+            <Content>{}</Content>\
+            </xml>",
+            cdata(&self.to_user_name),
+            cdata(&self.from_user_name),
+            self.create_time,
+            cdata(&self.content)
+        )
+    }
+}
@@ -0,0 +1,113 @@
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use serde::{Deserialize, Serialize};
+
+use std::env;
+
+use crate::auxiliary::read_config_or;
+use crate::notifications::{stage_message, NotificationEvent, Notifier};
+
+lazy_static! {
+    static ref SMTP_HOST: Option<String> = env::var("SMTP_HOST").ok();
+}
+
+pub fn is_email_configured() -> bool {
+    SMTP_HOST.is_some()
+}
+
+pub fn is_valid_email(email: &str) -> bool {
+    email.parse::<Address>().is_ok()
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmailPayload {
+    subject: String,
+    body: String,
+}
+
+pub fn build_email_payload(event: NotificationEvent, product_barcode: &str) -> Option<String> {
+    let message = stage_message(event)?;
+    let body = match event {
+        NotificationEvent::StageChanged(_) => format!(
+            "您好：\n\n您的试剂盒{}状态已更新：{}。\n{}\n",
+            product_barcode, message.description, message.remark
+        ),
+        NotificationEvent::Reminder(_) => format!(
+            "您好：\n\n您的试剂盒{}{}。\n{}\n",
+            product_barcode, message.description, message.remark
+        ),
+    };
+    serde_json::to_string(&EmailPayload {
+        subject: format!("{}（试剂盒{}）", message.title, product_barcode),
+        body,
+    })
+    .ok()
+}
+
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from_address: String,
+}
+
+impl EmailNotifier {
+    /// Returns `None` when SMTP_HOST is not set, which disables the email channel.
+    pub fn load() -> Option<Self> {
+        let host = SMTP_HOST.as_ref()?;
+        let builder = match read_config_or("SMTP_TLS", "starttls".to_string())
+            .to_lowercase()
+            .as_ref()
+        {
+            "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        };
+        let mut builder = match builder {
+            Ok(builder) => builder,
+            Err(error) => {
+                error!("SMTP配置错误：{:?}", error);
+                return None;
+            }
+        };
+        if let Ok(port) = env::var("SMTP_PORT") {
+            builder = builder.port(port.parse().expect("SMTP_PORT格式错误"));
+        }
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Some(Self {
+            transport: builder.build(),
+            from_address: env::var("SMTP_FROM").expect("未设置SMTP_FROM"),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl Notifier for EmailNotifier {
+    async fn send(&self, recipient: &str, payload: &str) -> Result<(), String> {
+        let payload: EmailPayload = serde_json::from_str(payload)
+            .map_err(|error| format!("邮件内容解析失败：{}", error))?;
+        let email = Message::builder()
+            .from(
+                self.from_address
+                    .parse()
+                    .map_err(|error| format!("发件地址错误：{}", error))?,
+            )
+            .to(recipient
+                .parse()
+                .map_err(|error| format!("收件地址错误：{}", error))?)
+            .subject(payload.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(payload.body)
+            .map_err(|error| format!("邮件构建失败：{}", error))?;
+        self.transport
+            .send(email)
+            .await
+            .map(|_| ())
+            .map_err(|error| format!("邮件发送失败：{}", error))
+    }
+}
@@ -0,0 +1,19 @@
+mod email;
+mod notifier;
+mod outbox;
+mod partner_webhooks;
+mod product_events;
+mod reminders;
+mod sms;
+mod webhook;
+mod wechat_template;
+
+pub use email::*;
+pub use notifier::*;
+pub use outbox::*;
+pub use partner_webhooks::*;
+pub use product_events::*;
+pub use reminders::*;
+pub use sms::*;
+pub use webhook::*;
+pub use wechat_template::*;
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use crate::auxiliary::{SmsSender, WechatAccessTokenState};
+use crate::models::{NotificationChannelEnum, StageEnum};
+use crate::notifications::{EmailNotifier, SmsNotifier, WebhookNotifier, WechatTemplateNotifier};
+
+/// Errors are returned as text so they can be kept as the notification's last_error.
+#[rocket::async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, recipient: &str, payload: &str) -> Result<(), String>;
+}
+
+/// What a notification is about, which decides its wording and template.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NotificationEvent {
+    StageChanged(StageEnum),
+    Reminder(StageEnum),
+}
+
+impl NotificationEvent {
+    pub fn stage(self) -> StageEnum {
+        match self {
+            Self::StageChanged(stage) | Self::Reminder(stage) => stage,
+        }
+    }
+}
+
+pub struct StageMessage {
+    pub title: &'static str,
+    pub description: &'static str,
+    pub remark: &'static str,
+}
+
+/// Returns `None` for events nobody gets notified about.
+pub fn stage_message(event: NotificationEvent) -> Option<StageMessage> {
+    match event {
+        NotificationEvent::StageChanged(StageEnum::Sampled) => Some(StageMessage {
+            title: "您的样本已被接收",
+            description: "样本已采集，正在检测",
+            remark: "检测完成后我们会第一时间通知您。",
+        }),
+        NotificationEvent::StageChanged(StageEnum::Finished) => Some(StageMessage {
+            title: "您的检测报告已出具",
+            description: "检测已完成",
+            remark: "请登录查看报告详情。",
+        }),
+        NotificationEvent::Reminder(StageEnum::Submitted) => Some(StageMessage {
+            title: "请尽快完成采样",
+            description: "已提交信息，尚未记录采样时间",
+            remark: "采样后请及时记录采样时间并寄回样本。",
+        }),
+        NotificationEvent::Reminder(StageEnum::Sampled) => Some(StageMessage {
+            title: "请尽快寄回样本",
+            description: "样本已采集，尚未送达实验室",
+            remark: "请尽快将样本寄回，以免影响检测结果。",
+        }),
+        _ => None,
+    }
+}
+
+pub struct NotifierSet {
+    wechat: Arc<dyn Notifier>,
+    email: Option<Arc<dyn Notifier>>,
+    sms: Arc<dyn Notifier>,
+    webhook: Arc<dyn Notifier>,
+}
+
+impl NotifierSet {
+    pub fn load(
+        access_token_state: WechatAccessTokenState,
+        sms_sender: Arc<dyn SmsSender>,
+    ) -> Self {
+        Self {
+            wechat: Arc::new(WechatTemplateNotifier::new(access_token_state)),
+            email: EmailNotifier::load().map(|notifier| Arc::new(notifier) as Arc<dyn Notifier>),
+            sms: Arc::new(SmsNotifier::new(sms_sender)),
+            webhook: Arc::new(WebhookNotifier::new()),
+        }
+    }
+
+    pub fn notifier(&self, channel: NotificationChannelEnum) -> Option<Arc<dyn Notifier>> {
+        match channel {
+            NotificationChannelEnum::Wechat => Some(self.wechat.clone()),
+            NotificationChannelEnum::Email => self.email.clone(),
+            NotificationChannelEnum::Sms => Some(self.sms.clone()),
+            NotificationChannelEnum::Webhook => Some(self.webhook.clone()),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::prelude::*;
+use chrono::Duration;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use serde::{Deserialize, Serialize};
+
+use crate::auxiliary::{read_config_or, GenericError};
+use crate::database::{self, MainDatabaseConnection};
+use crate::jobs::{BackgroundJob, JobContext};
+use crate::models::*;
+use crate::notifications::{
+    build_email_payload, build_sms_payload, build_webhook_payload, build_wechat_template_payload,
+    find_wechat_openid, is_email_configured, NotificationEvent, NotifierSet,
+};
+
+lazy_static! {
+    pub static ref NOTIFICATION_POLL_SECONDS: u64 = read_config_or("NOTIFICATION_POLL_SECONDS", 10);
+    static ref NOTIFICATION_BATCH_SIZE: i64 = read_config_or("NOTIFICATION_BATCH_SIZE", 20);
+    static ref NOTIFICATION_MAX_ATTEMPTS: i32 = read_config_or("NOTIFICATION_MAX_ATTEMPTS", 6);
+    static ref NOTIFICATION_RETRY_BASE_SECONDS: i64 =
+        read_config_or("NOTIFICATION_RETRY_BASE_SECONDS", 60);
+    static ref NOTIFICATION_RETRY_MAX_SECONDS: i64 =
+        read_config_or("NOTIFICATION_RETRY_MAX_SECONDS", 21600);
+    // Claimed notifications are skipped by other workers until the lease runs out
+    static ref NOTIFICATION_LEASE_SECONDS: i64 = read_config_or("NOTIFICATION_LEASE_SECONDS", 300);
+}
+
+pub fn load_notification_preference(
+    c: &PgConnection,
+    user_id: i32,
+) -> QueryResult<NotificationPreference> {
+    Ok(database::notification_preferences::table
+        .find(user_id)
+        .get_result(c)
+        .optional()?
+        .unwrap_or_else(|| NotificationPreference::default_for(user_id, Utc::now().naive_utc())))
+}
+
+pub fn enqueue_stage_notifications(c: &PgConnection, product: &Product) -> QueryResult<usize> {
+    enqueue_notifications(
+        c,
+        product,
+        NotificationEvent::StageChanged(product.current_stage),
+        None,
+    )
+}
+
+/// Queues one notification per channel the kit owner opted into.
+pub fn enqueue_notifications(
+    c: &PgConnection,
+    product: &Product,
+    event: NotificationEvent,
+    reminder_id: Option<i32>,
+) -> QueryResult<usize> {
+    let product_barcode = product.product_barcode.as_str();
+    let profile_id = match product.profile_id {
+        Some(profile_id) => profile_id,
+        None => return Ok(0),
+    };
+    let user: User = database::users::table
+        .inner_join(database::profiles::table)
+        .filter(database::profiles::id.eq(profile_id))
+        .select(database::users::all_columns)
+        .get_result(c)?;
+    let user_id = user.id;
+    let preference = load_notification_preference(c, user_id)?;
+    let stage = event.stage();
+
+    let mut targets: Vec<(NotificationChannelEnum, String, Option<String>)> = Vec::new();
+    if preference.wechat_enabled {
+        if let Some(openid) = find_wechat_openid(c, user_id)? {
+            let payload = build_wechat_template_payload(&openid, event, product_barcode);
+            targets.push((NotificationChannelEnum::Wechat, openid, payload));
+        }
+    }
+    if let (true, true, Some(email)) = (
+        preference.email_enabled,
+        is_email_configured(),
+        preference.email,
+    ) {
+        let payload = build_email_payload(event, product_barcode);
+        targets.push((NotificationChannelEnum::Email, email, payload));
+    }
+    if let (true, Some(phone_number)) = (preference.sms_enabled, user.phone_number) {
+        let payload = build_sms_payload(event, product_barcode);
+        targets.push((NotificationChannelEnum::Sms, phone_number, payload));
+    }
+    if let (true, Some(webhook_url)) = (preference.webhook_enabled, preference.webhook_url) {
+        let payload = build_webhook_payload(event, product_barcode);
+        targets.push((NotificationChannelEnum::Webhook, webhook_url, payload));
+    }
+
+    let current_time = Utc::now().naive_utc();
+    let new_notifications: Vec<NewNotification> = targets
+        .into_iter()
+        .filter_map(|(channel, recipient, payload)| {
+            Some(NewNotification {
+                user_id,
+                product_id: product.id,
+                channel,
+                recipient,
+                event_stage: stage,
+                payload: payload?,
+                created_time: current_time,
+                next_attempt_time: current_time,
+                reminder_id,
+            })
+        })
+        .collect();
+    diesel::insert_into(database::notifications::table)
+        .values(&new_notifications)
+        .execute(c)
+}
+
+fn claim_due_notifications(c: &PgConnection) -> QueryResult<Vec<Notification>> {
+    c.transaction(|| {
+        let current_time = Utc::now().naive_utc();
+        let due_notifications: Vec<Notification> = database::notifications::table
+            .filter(database::notifications::status.eq(NotificationStatusEnum::Pending))
+            .filter(database::notifications::next_attempt_time.le(current_time))
+            .order(database::notifications::next_attempt_time)
+            .limit(*NOTIFICATION_BATCH_SIZE)
+            .for_update()
+            .skip_locked()
+            .get_results(c)?;
+        let due_ids: Vec<i32> = due_notifications
+            .iter()
+            .map(|notification| notification.id)
+            .collect();
+        diesel::update(
+            database::notifications::table.filter(database::notifications::id.eq_any(due_ids)),
+        )
+        .set(
+            database::notifications::next_attempt_time
+                .eq(current_time + Duration::seconds(*NOTIFICATION_LEASE_SECONDS)),
+        )
+        .execute(c)?;
+        Ok(due_notifications)
+    })
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let multiplier = 2i64.saturating_pow((attempts - 1).max(0) as u32);
+    Duration::seconds(
+        NOTIFICATION_RETRY_BASE_SECONDS
+            .saturating_mul(multiplier)
+            .min(*NOTIFICATION_RETRY_MAX_SECONDS),
+    )
+}
+
+fn record_delivery_result(
+    c: &PgConnection,
+    notification: &Notification,
+    result: Result<(), String>,
+) -> QueryResult<usize> {
+    let current_time = Utc::now().naive_utc();
+    let attempts = notification.attempts + 1;
+    let target = database::notifications::table.find(notification.id);
+    match result {
+        Ok(_) => diesel::update(target)
+            .set((
+                database::notifications::status.eq(NotificationStatusEnum::Sent),
+                database::notifications::attempts.eq(attempts),
+                database::notifications::last_error.eq(None::<String>),
+                database::notifications::sent_time.eq(Some(current_time)),
+            ))
+            .execute(c),
+        Err(error) => {
+            let (status, next_attempt_time) = if attempts >= *NOTIFICATION_MAX_ATTEMPTS {
+                warn!("通知{}多次发送失败：{}", notification.id, error);
+                (NotificationStatusEnum::Failed, current_time)
+            } else {
+                (
+                    NotificationStatusEnum::Pending,
+                    current_time + retry_delay(attempts),
+                )
+            };
+            diesel::update(target)
+                .set((
+                    database::notifications::status.eq(status),
+                    database::notifications::attempts.eq(attempts),
+                    database::notifications::last_error.eq(Some(error)),
+                    database::notifications::next_attempt_time.eq(next_attempt_time),
+                ))
+                .execute(c)
+        }
+    }
+}
+
+async fn deliver_notification(
+    notifier_set: &NotifierSet,
+    notification: &Notification,
+) -> Result<(), String> {
+    match notifier_set.notifier(notification.channel) {
+        Some(notifier) => {
+            notifier
+                .send(&notification.recipient, &notification.payload)
+                .await
+        }
+        None => Err(format!("{:?}通知渠道未配置", notification.channel)),
+    }
+}
+
+async fn deliver_due_notifications(
+    db: &MainDatabaseConnection,
+    notifier_set: &NotifierSet,
+) -> Result<usize, GenericError> {
+    let due_notifications = db.run(|c| claim_due_notifications(c)).await?;
+    let delivered_count = due_notifications.len();
+    for notification in due_notifications {
+        let result = deliver_notification(notifier_set, &notification).await;
+        db.run(move |c| record_delivery_result(c, &notification, result))
+            .await?;
+    }
+    Ok(delivered_count)
+}
+
+pub fn retry_failed_notification(c: &PgConnection, notification_id: i32) -> QueryResult<usize> {
+    diesel::update(
+        database::notifications::table
+            .find(notification_id)
+            .filter(database::notifications::status.eq(NotificationStatusEnum::Failed)),
+    )
+    .set((
+        database::notifications::status.eq(NotificationStatusEnum::Pending),
+        database::notifications::attempts.eq(0),
+        database::notifications::next_attempt_time.eq(Utc::now().naive_utc()),
+    ))
+    .execute(c)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DeliverDueNotifications;
+
+#[rocket::async_trait]
+impl BackgroundJob for DeliverDueNotifications {
+    const JOB_TYPE: &'static str = "notification.deliver_due";
+
+    async fn run(self, context: &JobContext) -> Result<(), String> {
+        loop {
+            match deliver_due_notifications(&context.db, &context.notifier_set).await {
+                Ok(0) => return Ok(()),
+                Ok(_) => (),
+                Err(error) => return Err(format!("{:?}", error)),
+            }
+        }
+    }
+}
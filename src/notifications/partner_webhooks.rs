@@ -0,0 +1,286 @@
+use chrono::prelude::*;
+use chrono::Duration;
+
+use data_encoding::HEXLOWER;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use hmac::{Hmac, Mac, NewMac};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use sha2::Sha256;
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::auxiliary::{read_config_or, GenericError};
+use crate::database::{self, MainDatabaseConnection};
+use crate::jobs::{BackgroundJob, JobContext};
+use crate::models::*;
+use crate::notifications::post_json;
+
+type HmacSha256 = Hmac<Sha256>;
+
+lazy_static! {
+    pub static ref PARTNER_WEBHOOK_POLL_SECONDS: u64 =
+        read_config_or("PARTNER_WEBHOOK_POLL_SECONDS", 10);
+    static ref PARTNER_WEBHOOK_BATCH_SIZE: i64 = read_config_or("PARTNER_WEBHOOK_BATCH_SIZE", 20);
+    static ref PARTNER_WEBHOOK_MAX_ATTEMPTS: i32 =
+        read_config_or("PARTNER_WEBHOOK_MAX_ATTEMPTS", 8);
+    static ref PARTNER_WEBHOOK_RETRY_BASE_SECONDS: i64 =
+        read_config_or("PARTNER_WEBHOOK_RETRY_BASE_SECONDS", 30);
+    static ref PARTNER_WEBHOOK_RETRY_MAX_SECONDS: i64 =
+        read_config_or("PARTNER_WEBHOOK_RETRY_MAX_SECONDS", 86400);
+    static ref PARTNER_WEBHOOK_LEASE_SECONDS: i64 =
+        read_config_or("PARTNER_WEBHOOK_LEASE_SECONDS", 300);
+}
+
+/// Hex HMAC-SHA256 of "<timestamp>.<payload>", sent as `X-MedKit-Signature: sha256=<hex>`.
+pub fn sign_webhook_payload(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC可接受任意长度的密钥");
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+    HEXLOWER.encode(&mac.finalize().into_bytes())
+}
+
+pub fn gen_webhook_secret() -> String {
+    format!(
+        "{}{}",
+        Uuid::new_v4().to_simple(),
+        Uuid::new_v4().to_simple()
+    )
+}
+
+/// Queues a delivery for every enabled subscription interested in the event.
+pub fn enqueue_partner_webhooks(
+    c: &PgConnection,
+    product: &Product,
+    event_type: &str,
+    event_time: NaiveDateTime,
+) -> QueryResult<usize> {
+    let subscriptions: Vec<WebhookSubscription> = database::webhook_subscriptions::table
+        .filter(database::webhook_subscriptions::enabled.eq(true))
+        .get_results(c)?;
+    // A kit can have a current report for each of its tests
+    let report_ids: Vec<Uuid> = database::reports::table
+        .filter(database::reports::product_id.eq(product.id))
+        .filter(database::reports::is_current.eq(true))
+        .select(database::reports::id)
+        .get_results(c)?;
+    let event_id = Uuid::new_v4();
+    let payload = json!({
+        "event_id": event_id,
+        "event_type": event_type,
+        "event_time": event_time,
+        "product": {
+            "product_barcode": product.product_barcode,
+            "current_stage": product.current_stage,
+            "report_ids": report_ids,
+        },
+    })
+    .to_string();
+    let new_deliveries: Vec<NewWebhookDelivery> = subscriptions
+        .into_iter()
+        .filter(|subscription| subscription.event_types.iter().any(|t| t == event_type))
+        .filter(|subscription| match &subscription.barcode_prefix {
+            Some(barcode_prefix) => product.product_barcode.starts_with(barcode_prefix.as_str()),
+            None => true,
+        })
+        .map(|subscription| NewWebhookDelivery {
+            subscription_id: subscription.id,
+            event_id,
+            event_type: event_type.to_string(),
+            payload: payload.to_owned(),
+            created_time: event_time,
+            next_attempt_time: event_time,
+        })
+        .collect();
+    diesel::insert_into(database::webhook_deliveries::table)
+        .values(&new_deliveries)
+        .execute(c)
+}
+
+/// Queues a fresh copy of a past delivery, keeping its event_id so partners can deduplicate.
+pub fn redeliver_webhook(c: &PgConnection, delivery_id: i32) -> QueryResult<WebhookDelivery> {
+    let delivery: WebhookDelivery = database::webhook_deliveries::table
+        .find(delivery_id)
+        .get_result(c)?;
+    let current_time = Utc::now().naive_utc();
+    diesel::insert_into(database::webhook_deliveries::table)
+        .values(NewWebhookDelivery {
+            subscription_id: delivery.subscription_id,
+            event_id: delivery.event_id,
+            event_type: delivery.event_type,
+            payload: delivery.payload,
+            created_time: current_time,
+            next_attempt_time: current_time,
+        })
+        .get_result(c)
+}
+
+fn claim_due_deliveries(
+    c: &PgConnection,
+) -> QueryResult<Vec<(WebhookDelivery, WebhookSubscription)>> {
+    c.transaction(|| {
+        let current_time = Utc::now().naive_utc();
+        let due_deliveries: Vec<WebhookDelivery> = database::webhook_deliveries::table
+            .filter(database::webhook_deliveries::status.eq(NotificationStatusEnum::Pending))
+            .filter(database::webhook_deliveries::next_attempt_time.le(current_time))
+            .order(database::webhook_deliveries::next_attempt_time)
+            .limit(*PARTNER_WEBHOOK_BATCH_SIZE)
+            .for_update()
+            .skip_locked()
+            .get_results(c)?;
+        let due_ids: Vec<i32> = due_deliveries.iter().map(|delivery| delivery.id).collect();
+        diesel::update(
+            database::webhook_deliveries::table
+                .filter(database::webhook_deliveries::id.eq_any(due_ids)),
+        )
+        .set(
+            database::webhook_deliveries::next_attempt_time
+                .eq(current_time + Duration::seconds(*PARTNER_WEBHOOK_LEASE_SECONDS)),
+        )
+        .execute(c)?;
+        let subscription_ids: Vec<i32> = due_deliveries
+            .iter()
+            .map(|delivery| delivery.subscription_id)
+            .collect();
+        let subscriptions: HashMap<i32, WebhookSubscription> =
+            database::webhook_subscriptions::table
+                .filter(database::webhook_subscriptions::id.eq_any(subscription_ids))
+                .get_results::<WebhookSubscription>(c)?
+                .into_iter()
+                .map(|subscription| (subscription.id, subscription))
+                .collect();
+        Ok(due_deliveries
+            .into_iter()
+            .filter_map(|delivery| {
+                let subscription = subscriptions.get(&delivery.subscription_id)?.clone();
+                Some((delivery, subscription))
+            })
+            .collect())
+    })
+}
+
+struct WebhookAttempt {
+    response_code: Option<i32>,
+    response_body: Option<String>,
+    error: Option<String>,
+}
+
+async fn attempt_delivery(
+    delivery: &WebhookDelivery,
+    subscription: &WebhookSubscription,
+) -> WebhookAttempt {
+    if !subscription.enabled {
+        return WebhookAttempt {
+            response_code: None,
+            response_body: None,
+            error: Some("订阅已停用".to_string()),
+        };
+    }
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_webhook_payload(&subscription.secret, timestamp, &delivery.payload);
+    let headers = [
+        ("X-MedKit-Event", delivery.event_type.to_owned()),
+        ("X-MedKit-Delivery", delivery.event_id.to_string()),
+        ("X-MedKit-Timestamp", timestamp.to_string()),
+        ("X-MedKit-Signature", format!("sha256={}", signature)),
+    ];
+    match post_json(&subscription.url, &delivery.payload, &headers).await {
+        Ok((status_code, body)) => WebhookAttempt {
+            response_code: Some(status_code as i32),
+            response_body: Some(body),
+            error: match status_code {
+                200..=299 => None,
+                _ => Some(format!("HTTP {}", status_code)),
+            },
+        },
+        Err(error) => WebhookAttempt {
+            response_code: None,
+            response_body: None,
+            error: Some(error),
+        },
+    }
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let multiplier = 2i64.saturating_pow((attempts - 1).max(0) as u32);
+    Duration::seconds(
+        PARTNER_WEBHOOK_RETRY_BASE_SECONDS
+            .saturating_mul(multiplier)
+            .min(*PARTNER_WEBHOOK_RETRY_MAX_SECONDS),
+    )
+}
+
+fn record_delivery_attempt(
+    c: &PgConnection,
+    delivery: &WebhookDelivery,
+    subscription_enabled: bool,
+    attempt: WebhookAttempt,
+) -> QueryResult<usize> {
+    let current_time = Utc::now().naive_utc();
+    let attempts = delivery.attempts + 1;
+    let (status, next_attempt_time, delivered_time) = match &attempt.error {
+        None => (
+            NotificationStatusEnum::Sent,
+            current_time,
+            Some(current_time),
+        ),
+        Some(_) if !subscription_enabled || attempts >= *PARTNER_WEBHOOK_MAX_ATTEMPTS => {
+            (NotificationStatusEnum::Failed, current_time, None)
+        }
+        Some(_) => (
+            NotificationStatusEnum::Pending,
+            current_time + retry_delay(attempts),
+            None,
+        ),
+    };
+    diesel::update(database::webhook_deliveries::table.find(delivery.id))
+        .set((
+            database::webhook_deliveries::status.eq(status),
+            database::webhook_deliveries::attempts.eq(attempts),
+            database::webhook_deliveries::response_code.eq(attempt.response_code),
+            database::webhook_deliveries::response_body.eq(attempt.response_body),
+            database::webhook_deliveries::last_error.eq(attempt.error),
+            database::webhook_deliveries::next_attempt_time.eq(next_attempt_time),
+            database::webhook_deliveries::delivered_time.eq(delivered_time),
+        ))
+        .execute(c)
+}
+
+async fn deliver_due_webhooks(db: &MainDatabaseConnection) -> Result<usize, GenericError> {
+    let due_deliveries = db.run(|c| claim_due_deliveries(c)).await?;
+    let delivered_count = due_deliveries.len();
+    for (delivery, subscription) in due_deliveries {
+        let attempt = attempt_delivery(&delivery, &subscription).await;
+        if let Some(error) = &attempt.error {
+            info!("Webhook投递{}失败：{}", delivery.id, error);
+        }
+        let subscription_enabled = subscription.enabled;
+        db.run(move |c| record_delivery_attempt(c, &delivery, subscription_enabled, attempt))
+            .await?;
+    }
+    Ok(delivered_count)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DeliverDuePartnerWebhooks;
+
+#[rocket::async_trait]
+impl BackgroundJob for DeliverDuePartnerWebhooks {
+    const JOB_TYPE: &'static str = "partner_webhook.deliver_due";
+
+    async fn run(self, context: &JobContext) -> Result<(), String> {
+        loop {
+            match deliver_due_webhooks(&context.db).await {
+                Ok(0) => return Ok(()),
+                Ok(_) => (),
+                Err(error) => return Err(format!("{:?}", error)),
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::prelude::*;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use crate::database;
+use crate::jobs::enqueue_job;
+use crate::models::Product;
+use crate::notifications::{
+    enqueue_partner_webhooks, enqueue_stage_notifications, DeliverDueNotifications,
+    DeliverDuePartnerWebhooks,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProductEvent {
+    StageChanged,
+    ReportAttached,
+    ReportRetracted,
+}
+
+pub const PRODUCT_EVENT_TYPES: [&str; 3] = [
+    "product.stage_changed",
+    "product.report_attached",
+    "product.report_retracted",
+];
+
+impl ProductEvent {
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::StageChanged => PRODUCT_EVENT_TYPES[0],
+            Self::ReportAttached => PRODUCT_EVENT_TYPES[1],
+            Self::ReportRetracted => PRODUCT_EVENT_TYPES[2],
+        }
+    }
+}
+
+/// Every change of products.current_stage, and every report released or retracted through
+/// reports.is_current, goes through here in the same transaction as the change itself.
+pub fn emit_product_events(
+    c: &PgConnection,
+    product_barcode: &str,
+    events: &[ProductEvent],
+) -> QueryResult<()> {
+    let product: Product = database::products::table
+        .filter(database::products::product_barcode.eq(product_barcode))
+        .get_result(c)?;
+    let event_time = Utc::now().naive_utc();
+    let mut notification_count = 0;
+    let mut webhook_count = 0;
+    for event in events {
+        if *event == ProductEvent::StageChanged {
+            notification_count += enqueue_stage_notifications(c, &product)?;
+        }
+        webhook_count += enqueue_partner_webhooks(c, &product, event.event_type(), event_time)?;
+    }
+    // Deliver right away instead of waiting for the next scheduled run
+    if notification_count > 0 {
+        enqueue_job(c, &DeliverDueNotifications)?;
+    }
+    if webhook_count > 0 {
+        enqueue_job(c, &DeliverDuePartnerWebhooks)?;
+    }
+    Ok(())
+}
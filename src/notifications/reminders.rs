@@ -0,0 +1,140 @@
+use chrono::prelude::*;
+use chrono::Duration;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::auxiliary::read_config_or;
+use crate::database;
+use crate::jobs::{enqueue_job, BackgroundJob, JobContext};
+use crate::models::*;
+use crate::notifications::{enqueue_notifications, DeliverDueNotifications, NotificationEvent};
+
+lazy_static! {
+    pub static ref REMINDER_CHECK_SECONDS: u64 = read_config_or("REMINDER_CHECK_SECONDS", 3600);
+    static ref REMINDER_SUBMITTED_AFTER_HOURS: i64 =
+        read_config_or("REMINDER_SUBMITTED_AFTER_HOURS", 72);
+    static ref REMINDER_SAMPLED_AFTER_HOURS: i64 =
+        read_config_or("REMINDER_SAMPLED_AFTER_HOURS", 120);
+    static ref REMINDER_INTERVAL_HOURS: i64 = read_config_or("REMINDER_INTERVAL_HOURS", 72);
+    static ref REMINDER_MAX_PER_PRODUCT: usize = read_config_or("REMINDER_MAX_PER_PRODUCT", 3);
+}
+
+// Submitted kits count from profile submission, sampled kits from the recorded sample time
+fn find_stuck_products(c: &PgConnection, current_time: NaiveDateTime) -> QueryResult<Vec<Product>> {
+    let submitted_before = current_time - Duration::hours(*REMINDER_SUBMITTED_AFTER_HOURS);
+    let sampled_before = current_time - Duration::hours(*REMINDER_SAMPLED_AFTER_HOURS);
+    database::products::table
+        .inner_join(database::profiles::table)
+        .filter(
+            database::products::current_stage
+                .eq(StageEnum::Submitted)
+                .and(database::profiles::submit_time.le(submitted_before))
+                .or(database::products::current_stage
+                    .eq(StageEnum::Sampled)
+                    .and(database::profiles::sample_time.le(sampled_before))),
+        )
+        .select(database::products::all_columns)
+        .get_results(c)
+}
+
+fn find_due_products(c: &PgConnection, current_time: NaiveDateTime) -> QueryResult<Vec<Product>> {
+    let stuck_products = find_stuck_products(c, current_time)?;
+    let product_ids: Vec<i32> = stuck_products.iter().map(|product| product.id).collect();
+    let mut reminder_history: HashMap<i32, Vec<NaiveDateTime>> = HashMap::new();
+    for (product_id, created_time) in database::product_reminders::table
+        .filter(database::product_reminders::product_id.eq_any(product_ids))
+        .select((
+            database::product_reminders::product_id,
+            database::product_reminders::created_time,
+        ))
+        .get_results::<(i32, NaiveDateTime)>(c)?
+    {
+        reminder_history
+            .entry(product_id)
+            .or_default()
+            .push(created_time);
+    }
+    let reminded_before = current_time - Duration::hours(*REMINDER_INTERVAL_HOURS);
+    Ok(stuck_products
+        .into_iter()
+        .filter(|product| match reminder_history.get(&product.id) {
+            Some(history) => {
+                history.len() < *REMINDER_MAX_PER_PRODUCT
+                    && history
+                        .iter()
+                        .all(|created_time| *created_time <= reminded_before)
+            }
+            None => *REMINDER_MAX_PER_PRODUCT > 0,
+        })
+        .collect())
+}
+
+// Only kept in the history if at least one channel could take the reminder
+fn send_reminder(
+    c: &PgConnection,
+    product: &Product,
+    current_time: NaiveDateTime,
+) -> QueryResult<bool> {
+    let result = c.transaction(|| {
+        let reminder: ProductReminder = diesel::insert_into(database::product_reminders::table)
+            .values(NewProductReminder {
+                product_id: product.id,
+                stage: product.current_stage,
+                created_time: current_time,
+            })
+            .get_result(c)?;
+        match enqueue_notifications(
+            c,
+            product,
+            NotificationEvent::Reminder(product.current_stage),
+            Some(reminder.id),
+        )? {
+            0 => Err(diesel::result::Error::RollbackTransaction),
+            _ => Ok(()),
+        }
+    });
+    match result {
+        Ok(_) => Ok(true),
+        Err(diesel::result::Error::RollbackTransaction) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn send_stage_reminders(c: &PgConnection) -> QueryResult<usize> {
+    let current_time = Utc::now().naive_utc();
+    let mut reminded_count = 0;
+    for product in find_due_products(c, current_time)? {
+        if send_reminder(c, &product, current_time)? {
+            reminded_count += 1;
+        }
+    }
+    if reminded_count > 0 {
+        enqueue_job(c, &DeliverDueNotifications)?;
+    }
+    Ok(reminded_count)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SendStageReminders;
+
+#[rocket::async_trait]
+impl BackgroundJob for SendStageReminders {
+    const JOB_TYPE: &'static str = "notification.send_stage_reminders";
+
+    async fn run(self, context: &JobContext) -> Result<(), String> {
+        let reminded_count = context
+            .db
+            .run(|c| send_stage_reminders(c))
+            .await
+            .map_err(|error| error.to_string())?;
+        if reminded_count > 0 {
+            info!("已向{}个试剂盒发送提醒", reminded_count);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use crate::auxiliary::SmsSender;
+use crate::notifications::{stage_message, NotificationEvent, Notifier};
+
+pub fn build_sms_payload(event: NotificationEvent, product_barcode: &str) -> Option<String> {
+    let message = stage_message(event)?;
+    Some(format!(
+        "【MedKit】您的试剂盒{}{}。{}",
+        product_barcode, message.description, message.remark
+    ))
+}
+
+pub struct SmsNotifier {
+    sender: Arc<dyn SmsSender>,
+}
+
+impl SmsNotifier {
+    pub fn new(sender: Arc<dyn SmsSender>) -> Self {
+        Self { sender }
+    }
+}
+
+#[rocket::async_trait]
+impl Notifier for SmsNotifier {
+    async fn send(&self, recipient: &str, payload: &str) -> Result<(), String> {
+        self.sender
+            .send(recipient, payload)
+            .await
+            .map_err(|error| format!("短信发送失败：{:?}", error))
+    }
+}
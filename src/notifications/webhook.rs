@@ -0,0 +1,172 @@
+use chrono::prelude::*;
+
+use isahc::config::{Configurable, ResolveMap};
+use isahc::{self, AsyncReadResponseExt, HttpClient, Request};
+
+use rocket::tokio::net::lookup_host;
+
+use serde_json::json;
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::auxiliary::read_config_or;
+use crate::notifications::{stage_message, NotificationEvent, Notifier};
+
+lazy_static! {
+    static ref WEBHOOK_TIMEOUT_SECONDS: u64 = read_config_or("WEBHOOK_TIMEOUT_SECONDS", 10);
+}
+
+pub fn build_webhook_payload(event: NotificationEvent, product_barcode: &str) -> Option<String> {
+    stage_message(event)?;
+    let event_name = match event {
+        NotificationEvent::StageChanged(_) => "product.stage_changed",
+        NotificationEvent::Reminder(_) => "product.reminder",
+    };
+    Some(
+        json!({
+            "event": event_name,
+            "product_barcode": product_barcode,
+            "stage": event.stage(),
+            "event_time": Utc::now().naive_utc(),
+        })
+        .to_string(),
+    )
+}
+
+pub fn is_valid_webhook_url(url: &str) -> bool {
+    (url.starts_with("https://") || url.starts_with("http://"))
+        && url.parse::<isahc::http::Uri>().is_ok()
+}
+
+fn is_public_ipv4(address: Ipv4Addr) -> bool {
+    let octets = address.octets();
+    !(address.is_private()
+        || address.is_loopback()
+        || address.is_link_local()
+        || address.is_unspecified()
+        || address.is_multicast()
+        || address.is_broadcast()
+        || address.is_documentation()
+        || octets[0] == 0
+        // Shared address space (RFC 6598)
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64))
+}
+
+fn is_public_ipv6(address: Ipv6Addr) -> bool {
+    if let Some(mapped) = address.to_ipv4_mapped() {
+        return is_public_ipv4(mapped);
+    }
+    let first_segment = address.segments()[0];
+    !(address.is_loopback()
+        || address.is_unspecified()
+        || address.is_multicast()
+        // Unique local fc00::/7 and link-local fe80::/10
+        || (first_segment & 0xfe00) == 0xfc00
+        || (first_segment & 0xffc0) == 0xfe80)
+}
+
+pub fn is_public_ip(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_public_ipv4(address),
+        IpAddr::V6(address) => is_public_ipv6(address),
+    }
+}
+
+/// Webhook addresses set by users are only allowed to reach public https hosts, returns the
+/// host, port and address the delivery has to use so the name cannot be re-resolved to an
+/// internal address afterwards.
+pub async fn resolve_public_webhook_url(url: &str) -> Result<(String, u16, IpAddr), String> {
+    if !url.starts_with("https://") {
+        return Err("仅支持HTTPS地址".to_string());
+    }
+    let uri = url
+        .parse::<isahc::http::Uri>()
+        .map_err(|error| format!("地址解析失败：{}", error))?;
+    let host = uri
+        .host()
+        .ok_or_else(|| "地址缺少主机名".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri.port_u16().unwrap_or(443);
+    let addresses: Vec<IpAddr> = lookup_host((host.as_str(), port))
+        .await
+        .map_err(|error| format!("域名解析失败：{}", error))?
+        .map(|address| address.ip())
+        .collect();
+    match addresses.first() {
+        Some(address) if addresses.iter().all(|address| is_public_ip(*address)) => {
+            Ok((host, port, *address))
+        }
+        Some(_) => Err(format!("{}解析至内部地址", host)),
+        None => Err(format!("{}无解析结果", host)),
+    }
+}
+
+/// Returns the response status code and the beginning of the response body.
+pub async fn post_json(
+    url: &str,
+    payload: &str,
+    headers: &[(&str, String)],
+) -> Result<(u16, String), String> {
+    post_json_resolved(url, payload, headers, None).await
+}
+
+async fn post_json_resolved(
+    url: &str,
+    payload: &str,
+    headers: &[(&str, String)],
+    resolve: Option<ResolveMap>,
+) -> Result<(u16, String), String> {
+    let mut request = Request::post(url)
+        .header("Content-Type", "application/json")
+        .timeout(Duration::from_secs(*WEBHOOK_TIMEOUT_SECONDS));
+    for (name, value) in headers {
+        request = request.header(*name, value.as_str());
+    }
+    let request = request
+        .body(payload.to_owned())
+        .map_err(|error| format!("请求构建失败：{}", error))?;
+    let mut response = match resolve {
+        Some(resolve) => {
+            HttpClient::builder()
+                .dns_resolve(resolve)
+                .build()
+                .map_err(|error| format!("请求构建失败：{}", error))?
+                .send_async(request)
+                .await
+        }
+        None => isahc::send_async(request).await,
+    }
+    .map_err(|error| format!("请求失败：{}", error))?;
+    let body = response.text().await.unwrap_or_default();
+    Ok((
+        response.status().as_u16(),
+        body.chars().take(1000).collect(),
+    ))
+}
+
+pub struct WebhookNotifier;
+
+impl WebhookNotifier {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[rocket::async_trait]
+impl Notifier for WebhookNotifier {
+    async fn send(&self, recipient: &str, payload: &str) -> Result<(), String> {
+        let (host, port, address) = resolve_public_webhook_url(recipient).await?;
+        let resolve = ResolveMap::new().add(host, port, address);
+        match post_json_resolved(recipient, payload, &[], Some(resolve)).await? {
+            (status_code, _) if (200..300).contains(&status_code) => Ok(()),
+            (status_code, body) => Err(format!(
+                "HTTP {}：{}",
+                status_code,
+                body.chars().take(200).collect::<String>()
+            )),
+        }
+    }
+}
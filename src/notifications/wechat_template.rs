@@ -0,0 +1,124 @@
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use isahc::{self, AsyncReadResponseExt};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use std::env;
+
+use crate::auxiliary::{WechatAccessTokenState, QRCODE_DOMAIN_ROOT, WECHAT_API_BASE, WECHAT_APPID};
+use crate::database;
+use crate::models::StageEnum;
+use crate::notifications::{stage_message, NotificationEvent, Notifier};
+
+lazy_static! {
+    static ref WECHAT_TEMPLATE_SAMPLED_ID: Option<String> =
+        env::var("WECHAT_TEMPLATE_SAMPLED_ID").ok();
+    static ref WECHAT_TEMPLATE_FINISHED_ID: Option<String> =
+        env::var("WECHAT_TEMPLATE_FINISHED_ID").ok();
+    static ref WECHAT_TEMPLATE_REMINDER_ID: Option<String> =
+        env::var("WECHAT_TEMPLATE_REMINDER_ID").ok();
+}
+
+#[derive(Deserialize)]
+struct WechatSendResponse {
+    #[serde(default)]
+    errcode: i32,
+    #[serde(default)]
+    errmsg: String,
+}
+
+// Official Account openid of the user, falling back to the one stored before wechat_identities
+pub fn find_wechat_openid(c: &PgConnection, user_id: i32) -> QueryResult<Option<String>> {
+    let openid = database::wechat_identities::table
+        .filter(database::wechat_identities::user_id.eq(user_id))
+        .filter(database::wechat_identities::appid.eq(&*WECHAT_APPID))
+        .select(database::wechat_identities::openid)
+        .first::<String>(c)
+        .optional()?;
+    match openid {
+        Some(openid) => Ok(Some(openid)),
+        None => database::users::table
+            .find(user_id)
+            .select(database::users::wechat_id)
+            .first::<Option<String>>(c),
+    }
+}
+
+/// Returns `None` when no template is configured for the stage.
+pub fn build_wechat_template_payload(
+    openid: &str,
+    event: NotificationEvent,
+    product_barcode: &str,
+) -> Option<String> {
+    let template_id = match event {
+        NotificationEvent::StageChanged(StageEnum::Sampled) => {
+            WECHAT_TEMPLATE_SAMPLED_ID.as_ref()?
+        }
+        NotificationEvent::StageChanged(StageEnum::Finished) => {
+            WECHAT_TEMPLATE_FINISHED_ID.as_ref()?
+        }
+        NotificationEvent::Reminder(_) => WECHAT_TEMPLATE_REMINDER_ID.as_ref()?,
+        _ => return None,
+    };
+    let message = stage_message(event)?;
+    Some(
+        json!({
+            "touser": openid,
+            "template_id": template_id,
+            "url": format!("{}{}", *QRCODE_DOMAIN_ROOT, product_barcode),
+            "data": {
+                "first": { "value": message.title },
+                "keyword1": { "value": product_barcode },
+                "keyword2": { "value": message.description },
+                "remark": { "value": message.remark },
+            },
+        })
+        .to_string(),
+    )
+}
+
+pub struct WechatTemplateNotifier {
+    access_token_state: WechatAccessTokenState,
+}
+
+impl WechatTemplateNotifier {
+    pub fn new(access_token_state: WechatAccessTokenState) -> Self {
+        Self { access_token_state }
+    }
+}
+
+// The openid is already part of the template payload
+#[rocket::async_trait]
+impl Notifier for WechatTemplateNotifier {
+    async fn send(&self, _recipient: &str, payload: &str) -> Result<(), String> {
+        let access_token = self
+            .access_token_state
+            .get()
+            .await
+            .map_err(|_| "获取AccessToken失败".to_string())?;
+        let response: WechatSendResponse = isahc::post_async(
+            format!(
+                "{}/cgi-bin/message/template/send?access_token={}",
+                *WECHAT_API_BASE, access_token
+            ),
+            payload.to_owned(),
+        )
+        .await
+        .map_err(|error| format!("请求失败：{}", error))?
+        .json()
+        .await
+        .map_err(|error| format!("响应解析失败：{}", error))?;
+        match response.errcode {
+            0 => Ok(()),
+            // The token was revoked or expired early, the next attempt fetches a new one
+            40001 | 40014 | 42001 => {
+                self.access_token_state.invalidate().await;
+                Err(format!("{}：{}", response.errcode, response.errmsg))
+            }
+            _ => Err(format!("{}：{}", response.errcode, response.errmsg)),
+        }
+    }
+}
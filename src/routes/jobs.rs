@@ -0,0 +1,72 @@
+use crate::auth::AdminAuth;
+use crate::auxiliary::{GenericError, GenericResult, SuccessResponse};
+use crate::database::{self, MainDatabaseConnection};
+use crate::jobs::retry_dead_job;
+use crate::models::*;
+
+use diesel::prelude::*;
+
+use rocket::serde::json::Json;
+
+#[get("/get_jobs/<page>/<status>")]
+pub async fn get_filtered_jobs(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    page: i32,
+    status: JobStatusEnum,
+) -> GenericResult<Vec<Job>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::jobs::table
+                .filter(database::jobs::status.eq(status))
+                .order(database::jobs::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_jobs/<page>")]
+pub async fn get_jobs(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    page: i32,
+) -> GenericResult<Vec<Job>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::jobs::table
+                .order(database::jobs::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_job/<job_id>")]
+pub async fn get_job(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    job_id: i32,
+) -> GenericResult<Job> {
+    SuccessResponse::build(
+        db.run(move |c| database::jobs::table.find(job_id).get_result(c))
+            .await?,
+    )
+}
+
+#[post("/retry_job", data = "<retry_job_data>")]
+pub async fn retry_job(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    retry_job_data: Json<ClientRetryJobData>,
+) -> GenericResult<String> {
+    let job_id = retry_job_data.job_id;
+    match db.run(move |c| retry_dead_job(c, job_id)).await? {
+        1 => SuccessResponse::build("完成".to_string()),
+        _ => Err(GenericError::InvalidInputError),
+    }
+}
@@ -0,0 +1,208 @@
+use crate::auth::{AdminAuth, StaffAuth};
+use crate::auxiliary::{GenericError, GenericResult, SuccessResponse};
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::*;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use rocket::serde::json::Json;
+
+fn check_kit_type_data(kit_type_data: &ClientKitTypeData) -> Result<(), GenericError> {
+    let name = kit_type_data.name.trim();
+    if name.is_empty() || name.chars().count() > 64 || kit_type_data.test_type_ids.len() > 50 {
+        return Err(GenericError::InvalidInputError);
+    }
+    Ok(())
+}
+
+fn save_kit_type_tests(
+    c: &PgConnection,
+    kit_type_id: i32,
+    test_type_ids: &[i32],
+) -> Result<(), GenericError> {
+    let mut test_type_ids = test_type_ids.to_vec();
+    test_type_ids.sort_unstable();
+    test_type_ids.dedup();
+    let found_count: i64 = database::test_types::table
+        .filter(database::test_types::id.eq_any(&test_type_ids))
+        .count()
+        .get_result(c)?;
+    if found_count as usize != test_type_ids.len() {
+        return Err(GenericError::TestTypeNotFoundError);
+    }
+    diesel::delete(
+        database::kit_type_tests::table
+            .filter(database::kit_type_tests::kit_type_id.eq(kit_type_id)),
+    )
+    .execute(c)?;
+    let new_tests: Vec<NewKitTypeTest> = test_type_ids
+        .into_iter()
+        .map(|test_type_id| NewKitTypeTest {
+            kit_type_id,
+            test_type_id,
+        })
+        .collect();
+    diesel::insert_into(database::kit_type_tests::table)
+        .values(new_tests)
+        .execute(c)?;
+    Ok(())
+}
+
+fn load_kit_type_detail(c: &PgConnection, kit_type: KitType) -> QueryResult<KitTypeDetail> {
+    let test_types: Vec<TestType> = database::kit_type_tests::table
+        .inner_join(database::test_types::table)
+        .filter(database::kit_type_tests::kit_type_id.eq(kit_type.id))
+        .select(database::test_types::all_columns)
+        .order(database::test_types::id)
+        .get_results(c)?;
+    Ok(KitTypeDetail {
+        id: kit_type.id,
+        name: kit_type.name,
+        test_types,
+    })
+}
+
+#[post("/create_test_type", data = "<test_type_data>")]
+pub async fn create_test_type(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    test_type_data: Json<NewTestType>,
+) -> GenericResult<TestType> {
+    let code = test_type_data.code.trim().to_string();
+    let name = test_type_data.name.trim().to_string();
+    // The code is passed around in upload URLs, so it is kept simple
+    if code.is_empty()
+        || code.len() > 32
+        || !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        || name.is_empty()
+        || name.chars().count() > 64
+    {
+        return Err(GenericError::InvalidInputError);
+    }
+    SuccessResponse::build(
+        db.run(move |c| {
+            diesel::insert_into(database::test_types::table)
+                .values(NewTestType { code, name })
+                .get_result(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_test_types")]
+pub async fn get_test_types(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+) -> GenericResult<Vec<TestType>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::test_types::table
+                .order(database::test_types::id)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[post("/create_kit_type", data = "<kit_type_data>")]
+pub async fn create_kit_type(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    kit_type_data: Json<ClientKitTypeData>,
+) -> GenericResult<KitTypeDetail> {
+    let kit_type_data = kit_type_data.into_inner();
+    check_kit_type_data(&kit_type_data)?;
+    SuccessResponse::build(
+        db.run(move |c| {
+            c.transaction(|| {
+                let kit_type: KitType = diesel::insert_into(database::kit_types::table)
+                    .values(database::kit_types::name.eq(kit_type_data.name.trim()))
+                    .get_result(c)?;
+                save_kit_type_tests(c, kit_type.id, &kit_type_data.test_type_ids)?;
+                Ok::<_, GenericError>(load_kit_type_detail(c, kit_type)?)
+            })
+        })
+        .await?,
+    )
+}
+
+/// Only applies to reports reviewed from now on, kits that are already finished stay finished.
+#[post("/update_kit_type", data = "<kit_type_data>")]
+pub async fn update_kit_type(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    kit_type_data: Json<ClientUpdateKitTypeData>,
+) -> GenericResult<KitTypeDetail> {
+    let kit_type_id = kit_type_data.kit_type_id;
+    let kit_type_data = kit_type_data.into_inner().kit_type;
+    check_kit_type_data(&kit_type_data)?;
+    SuccessResponse::build(
+        db.run(move |c| {
+            c.transaction(|| {
+                let kit_type: KitType =
+                    diesel::update(database::kit_types::table.find(kit_type_id))
+                        .set(database::kit_types::name.eq(kit_type_data.name.trim()))
+                        .get_result(c)?;
+                save_kit_type_tests(c, kit_type.id, &kit_type_data.test_type_ids)?;
+                Ok::<_, GenericError>(load_kit_type_detail(c, kit_type)?)
+            })
+        })
+        .await?,
+    )
+}
+
+#[get("/get_kit_types")]
+pub async fn get_kit_types(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+) -> GenericResult<Vec<KitTypeDetail>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            let kit_types: Vec<KitType> = database::kit_types::table
+                .order(database::kit_types::id)
+                .get_results(c)?;
+            kit_types
+                .into_iter()
+                .map(|kit_type| load_kit_type_detail(c, kit_type))
+                .collect::<QueryResult<Vec<KitTypeDetail>>>()
+        })
+        .await?,
+    )
+}
+
+/// The expected tests decide when a kit is finished, so the kit type is fixed once a report
+/// has been submitted for the kit.
+#[post("/set_product_kit_type", data = "<product_kit_type_data>")]
+pub async fn set_product_kit_type(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    product_kit_type_data: Json<ClientSetProductKitTypeData>,
+) -> GenericResult<String> {
+    let product_kit_type_data = product_kit_type_data.into_inner();
+    db.run(move |c| {
+        c.transaction(|| {
+            let product: Product = database::products::table
+                .filter(
+                    database::products::product_barcode.eq(&product_kit_type_data.product_barcode),
+                )
+                .for_update()
+                .get_result(c)?;
+            let report_count: i64 = database::reports::table
+                .filter(database::reports::product_id.eq(product.id))
+                .count()
+                .get_result(c)?;
+            if report_count > 0 {
+                return Err(GenericError::ProductKitTypeLockedError);
+            }
+            diesel::update(database::products::table.find(product.id))
+                .set(database::products::kit_type_id.eq(product_kit_type_data.kit_type_id))
+                .execute(c)?;
+            Ok(())
+        })
+    })
+    .await?;
+    SuccessResponse::build("完成".to_string())
+}
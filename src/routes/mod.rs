@@ -1,15 +1,27 @@
 mod error_catchers;
+mod jobs;
+mod kit_types;
+mod notifications;
 mod product;
 mod profile;
+mod report_templates;
 mod reports;
+mod totp;
 mod user;
+mod webhooks;
 mod wechat_validation;
 
 use error_catchers::*;
+use jobs::*;
+use kit_types::*;
+use notifications::*;
 use product::*;
 use profile::*;
+use report_templates::*;
 use reports::*;
+use totp::*;
 use user::*;
+use webhooks::*;
 use wechat_validation::*;
 
 use rocket::{Catcher, Route};
@@ -22,11 +34,33 @@ pub fn user_routes() -> Vec<Route> {
         get_users,
         get_all_users,
         change_user_role,
+        set_report_reviewer,
         remove_user,
         logout,
         change_password,
         wechat_login,
-        get_user_statistics
+        wechat_miniprogram_login,
+        request_sms_code,
+        sms_login,
+        bind_wechat,
+        unbind_wechat,
+        merge_users,
+        get_user_statistics,
+        get_locked_users,
+        get_lockout_events,
+        clear_lock
+    ]
+}
+
+pub fn totp_routes() -> Vec<Route> {
+    routes![
+        enroll_totp,
+        activate_totp,
+        verify_totp_login,
+        disable_totp,
+        regenerate_recovery_codes,
+        get_totp_policies,
+        set_totp_policy
     ]
 }
 
@@ -38,6 +72,7 @@ pub fn product_routes() -> Vec<Route> {
         get_filtered_products,
         get_product,
         get_profile_by_product,
+        get_product_reminders,
         submit_sample_time,
         get_product_statistics
     ]
@@ -58,16 +93,79 @@ pub fn profile_routes() -> Vec<Route> {
 pub fn report_routes() -> Vec<Route> {
     routes![
         upload_report,
+        upload_report_form,
+        generate_report,
         get_reports,
         get_filtered_reports,
-        remove_report,
+        get_my_reports,
+        get_product_reports,
+        review_report,
+        get_pending_reviews,
+        retract_report,
+        get_report_versions,
         get_report,
+        download_report,
+        create_report_share_link,
+        download_shared_report,
+        verify_report,
+        download_report_signature,
+        verify_report_file_signature,
+        verify_report_file_signature_form,
+        get_report_downloads,
+        check_storage,
         publish_report,
     ]
 }
 
+pub fn report_template_routes() -> Vec<Route> {
+    routes![
+        create_report_template,
+        update_report_template,
+        remove_report_template,
+        get_report_templates,
+    ]
+}
+
+pub fn kit_type_routes() -> Vec<Route> {
+    routes![
+        create_test_type,
+        get_test_types,
+        create_kit_type,
+        update_kit_type,
+        get_kit_types,
+        set_product_kit_type,
+    ]
+}
+
+pub fn notification_routes() -> Vec<Route> {
+    routes![
+        get_notifications,
+        get_filtered_notifications,
+        retry_notification,
+        get_notification_preference,
+        set_notification_preference
+    ]
+}
+
+pub fn job_routes() -> Vec<Route> {
+    routes![get_jobs, get_filtered_jobs, get_job, retry_job]
+}
+
+pub fn webhook_routes() -> Vec<Route> {
+    routes![
+        create_webhook_subscription,
+        update_webhook_subscription,
+        rotate_webhook_secret,
+        remove_webhook_subscription,
+        get_webhook_subscriptions,
+        get_webhook_deliveries,
+        get_filtered_webhook_deliveries,
+        redeliver_webhook_delivery
+    ]
+}
+
 pub fn wechat_validation_routes() -> Vec<Route> {
-    routes![]
+    routes![validate_wechat_server, handle_wechat_message]
 }
 
 pub fn api_error_catchers() -> Vec<Catcher> {
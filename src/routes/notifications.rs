@@ -0,0 +1,152 @@
+use crate::auth::{StaffAuth, UserDigest};
+use crate::auxiliary::{GenericError, GenericResult, SuccessResponse};
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::*;
+use crate::notifications::{
+    is_valid_email, is_valid_webhook_url, load_notification_preference, resolve_public_webhook_url,
+    retry_failed_notification,
+};
+
+use diesel::prelude::*;
+
+use chrono::prelude::*;
+
+use rocket::serde::json::Json;
+
+#[get("/get_notifications/<page>/<status>")]
+pub async fn get_filtered_notifications(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    page: i32,
+    status: NotificationStatusEnum,
+) -> GenericResult<Vec<Notification>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::notifications::table
+                .filter(database::notifications::status.eq(status))
+                .order(database::notifications::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_notifications/<page>")]
+pub async fn get_notifications(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    page: i32,
+) -> GenericResult<Vec<Notification>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::notifications::table
+                .order(database::notifications::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[post("/retry_notification", data = "<retry_notification_data>")]
+pub async fn retry_notification(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    retry_notification_data: Json<ClientRetryNotificationData>,
+) -> GenericResult<String> {
+    let notification_id = retry_notification_data.notification_id;
+    match db
+        .run(move |c| retry_failed_notification(c, notification_id))
+        .await?
+    {
+        1 => SuccessResponse::build("完成".to_string()),
+        _ => Err(GenericError::InvalidInputError),
+    }
+}
+
+#[get("/get_preference")]
+pub async fn get_notification_preference(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+) -> GenericResult<NotificationPreference> {
+    let user_id = user_digest.user_id;
+    SuccessResponse::build(
+        db.run(move |c| load_notification_preference(c, user_id))
+            .await?,
+    )
+}
+
+#[post("/set_preference", data = "<preference_data>")]
+pub async fn set_notification_preference(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    preference_data: Json<ClientNotificationPreferenceData>,
+) -> GenericResult<NotificationPreference> {
+    let user_id = user_digest.user_id;
+    let preference_data = preference_data.into_inner();
+    let email = preference_data
+        .email
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+    let webhook_url = preference_data
+        .webhook_url
+        .map(|webhook_url| webhook_url.trim().to_string())
+        .filter(|webhook_url| !webhook_url.is_empty());
+    match &email {
+        Some(email) if !is_valid_email(email) => return Err(GenericError::InvalidEmailError),
+        None if preference_data.email_enabled => return Err(GenericError::InvalidEmailError),
+        _ => (),
+    }
+    match &webhook_url {
+        Some(webhook_url) if !is_valid_webhook_url(webhook_url) => {
+            return Err(GenericError::InvalidWebhookUrlError)
+        }
+        Some(webhook_url) => {
+            if let Err(error) = resolve_public_webhook_url(webhook_url).await {
+                warn!("用户{}设置的Webhook地址不可用：{}", user_id, error);
+                return Err(GenericError::WebhookUrlNotAllowedError);
+            }
+        }
+        None if preference_data.webhook_enabled => {
+            return Err(GenericError::InvalidWebhookUrlError)
+        }
+        None => (),
+    }
+    if preference_data.sms_enabled {
+        let phone_number: Option<String> = db
+            .run(move |c| {
+                database::users::table
+                    .find(user_id)
+                    .select(database::users::phone_number)
+                    .get_result(c)
+            })
+            .await?;
+        if phone_number.is_none() {
+            return Err(GenericError::PhoneNumberNotBoundError);
+        }
+    }
+    let new_preference = NewNotificationPreference {
+        user_id,
+        wechat_enabled: preference_data.wechat_enabled,
+        email_enabled: preference_data.email_enabled,
+        email,
+        sms_enabled: preference_data.sms_enabled,
+        webhook_enabled: preference_data.webhook_enabled,
+        webhook_url,
+        updated_time: Utc::now().naive_utc(),
+    };
+    SuccessResponse::build(
+        db.run(move |c| {
+            diesel::insert_into(database::notification_preferences::table)
+                .values(&new_preference)
+                .on_conflict(database::notification_preferences::user_id)
+                .do_update()
+                .set(&new_preference)
+                .get_result(c)
+        })
+        .await?,
+    )
+}
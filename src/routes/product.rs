@@ -1,9 +1,11 @@
 use crate::auth::{StaffAuth, UserDigest};
 use crate::auxiliary::{
     GenericError, GenericResult, ProductBarcode, ProductBarcodeGeneratorState, SuccessResponse,
+    QRCODE_DOMAIN_ROOT,
 };
 use crate::database::{self, MainDatabaseConnection};
 use crate::models::*;
+use crate::notifications::{emit_product_events, ProductEvent};
 
 use diesel::prelude::*;
 
@@ -13,13 +15,6 @@ use chrono::NaiveDateTime;
 use rocket::serde::json::Json;
 use rocket::State;
 
-use std::env;
-
-lazy_static! {
-    static ref QRCODE_DOMAIN_ROOT: String =
-        env::var("QRCODE_ROOT_DOMAIN").expect("未设置QRCODE_ROOT_DOMAIN");
-}
-
 #[get("/init_product")]
 pub async fn init_product(
     db: MainDatabaseConnection,
@@ -171,6 +166,26 @@ pub async fn get_profile_by_product(
     )
 }
 
+#[get("/get_reminders/<product_barcode>")]
+pub async fn get_product_reminders(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    product_barcode: ProductBarcode<'_>,
+) -> GenericResult<Vec<ProductReminder>> {
+    let barcode_input = product_barcode.inner().to_owned();
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::product_reminders::table
+                .inner_join(database::products::table)
+                .filter(database::products::product_barcode.eq(barcode_input))
+                .select(database::product_reminders::all_columns)
+                .order(database::product_reminders::id)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
 #[post("/submit_sample_time/<product_barcode>", data = "<sample_time_data>")]
 pub async fn submit_sample_time(
     db: MainDatabaseConnection,
@@ -211,14 +226,24 @@ pub async fn submit_sample_time(
                 if query_result.current_stage == StageEnum::Submitted
                     || query_result.current_stage == StageEnum::Sampled
                 {
+                    let previous_stage = query_result.current_stage;
                     match db
                         .run(move |c| {
-                            diesel::update(
-                                database::products::table
-                                    .filter(database::products::product_barcode.eq(input_barcode)),
-                            )
-                            .set(database::products::current_stage.eq(StageEnum::Sampled))
-                            .execute(c)
+                            c.transaction(|| {
+                                diesel::update(database::products::table.filter(
+                                    database::products::product_barcode.eq(&input_barcode),
+                                ))
+                                .set(database::products::current_stage.eq(StageEnum::Sampled))
+                                .execute(c)?;
+                                if previous_stage == StageEnum::Submitted {
+                                    emit_product_events(
+                                        c,
+                                        &input_barcode,
+                                        &[ProductEvent::StageChanged],
+                                    )?;
+                                }
+                                Ok::<_, diesel::result::Error>(())
+                            })
                         })
                         .await
                     {
@@ -2,6 +2,7 @@ use crate::auth::{StaffAuth, UserDigest};
 use crate::auxiliary::{GenericError, GenericResult, ProductBarcode, SuccessResponse};
 use crate::database::{self, MainDatabaseConnection};
 use crate::models::*;
+use crate::notifications::{emit_product_events, ProductEvent};
 
 use diesel::prelude::*;
 
@@ -26,7 +27,7 @@ pub async fn submit_profile_then_update(
                     database::products::product_barcode,
                     database::products::init_time,
                     database::products::current_stage,
-                    database::products::report_id,
+                    database::products::kit_type_id,
                 ))
                 .filter(database::products::product_barcode.eq_all(barcode_input))
                 .limit(1)
@@ -43,18 +44,26 @@ pub async fn submit_profile_then_update(
     new_profile.submit_time = current_timestamp;
     match db
         .run(move |c| {
-            diesel::insert_into(database::profiles::table)
-                .values(new_profile)
-                .get_result(c)
-                .and_then(|insert_result: Profile| {
-                    let update_set = UpdateProductAfterSubmission {
-                        profile_id: Some(insert_result.id),
-                        current_stage: StageEnum::Submitted,
-                    };
-                    diesel::update(database::products::table.find(query_result.id))
-                        .set(update_set)
-                        .execute(c)
-                })
+            c.transaction(|| {
+                let insert_result: Profile = diesel::insert_into(database::profiles::table)
+                    .values(new_profile)
+                    .get_result(c)?;
+                let update_set = UpdateProductAfterSubmission {
+                    profile_id: Some(insert_result.id),
+                    current_stage: StageEnum::Submitted,
+                };
+                let updated_count = diesel::update(database::products::table.find(query_result.id))
+                    .set(update_set)
+                    .execute(c)?;
+                if updated_count == 1 {
+                    emit_product_events(
+                        c,
+                        &query_result.product_barcode,
+                        &[ProductEvent::StageChanged],
+                    )?;
+                }
+                Ok::<_, diesel::result::Error>(updated_count)
+            })
         })
         .await?
     {
@@ -141,16 +150,24 @@ pub async fn bind_profile(
         } else {
             match db
                 .run(move |c| {
-                    diesel::update(
-                        database::products::table.filter(
-                            database::products::product_barcode.eq_all(barcode_input_clone),
-                        ),
-                    )
-                    .set((
-                        database::products::profile_id.eq_all(Some(profile_id)),
-                        database::products::current_stage.eq_all(StageEnum::Submitted),
-                    ))
-                    .execute(c)
+                    c.transaction(|| {
+                        let updated_count = diesel::update(database::products::table.filter(
+                            database::products::product_barcode.eq_all(&barcode_input_clone),
+                        ))
+                        .set((
+                            database::products::profile_id.eq_all(Some(profile_id)),
+                            database::products::current_stage.eq_all(StageEnum::Submitted),
+                        ))
+                        .execute(c)?;
+                        if updated_count == 1 {
+                            emit_product_events(
+                                c,
+                                &barcode_input_clone,
+                                &[ProductEvent::StageChanged],
+                            )?;
+                        }
+                        Ok::<_, diesel::result::Error>(updated_count)
+                    })
                 })
                 .await?
             {
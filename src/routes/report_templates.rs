@@ -0,0 +1,133 @@
+use crate::auth::{AdminAuth, StaffAuth};
+use crate::auxiliary::{GenericError, GenericResult, SuccessResponse};
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::*;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use chrono::prelude::*;
+
+use rocket::serde::json::Json;
+
+fn check_template_data(template_data: &ClientReportTemplateData) -> Result<(), GenericError> {
+    let name = template_data.name.trim();
+    if name.is_empty()
+        || name.chars().count() > 64
+        || template_data.title.trim().is_empty()
+        || template_data.body.trim().is_empty()
+        || template_data.body.chars().count() > 10000
+    {
+        return Err(GenericError::InvalidInputError);
+    }
+    Ok(())
+}
+
+// Only one template can be the default, the partial unique index enforces it
+fn clear_default_template(c: &PgConnection) -> QueryResult<usize> {
+    diesel::update(
+        database::report_templates::table.filter(database::report_templates::is_default.eq(true)),
+    )
+    .set(database::report_templates::is_default.eq(false))
+    .execute(c)
+}
+
+#[post("/create_template", data = "<template_data>")]
+pub async fn create_report_template(
+    db: MainDatabaseConnection,
+    admin: AdminAuth,
+    template_data: Json<ClientReportTemplateData>,
+) -> GenericResult<ReportTemplate> {
+    let template_data = template_data.into_inner();
+    check_template_data(&template_data)?;
+    let new_template = NewReportTemplate {
+        name: template_data.name.trim().to_string(),
+        title: template_data.title,
+        header: template_data.header,
+        body: template_data.body,
+        footer: template_data.footer,
+        is_default: template_data.is_default,
+        updated_by: Some(admin.user_id),
+        updated_time: Utc::now().naive_utc(),
+    };
+    SuccessResponse::build(
+        db.run(move |c| {
+            c.transaction(|| {
+                if new_template.is_default {
+                    clear_default_template(c)?;
+                }
+                diesel::insert_into(database::report_templates::table)
+                    .values(new_template)
+                    .get_result(c)
+            })
+        })
+        .await?,
+    )
+}
+
+#[post("/update_template", data = "<template_data>")]
+pub async fn update_report_template(
+    db: MainDatabaseConnection,
+    admin: AdminAuth,
+    template_data: Json<ClientUpdateReportTemplateData>,
+) -> GenericResult<ReportTemplate> {
+    let template_id = template_data.template_id;
+    let template_data = template_data.into_inner().template;
+    check_template_data(&template_data)?;
+    let update_set = UpdateReportTemplate {
+        name: template_data.name.trim().to_string(),
+        title: template_data.title,
+        header: template_data.header,
+        body: template_data.body,
+        footer: template_data.footer,
+        is_default: template_data.is_default,
+        updated_by: Some(admin.user_id),
+        updated_time: Utc::now().naive_utc(),
+    };
+    SuccessResponse::build(
+        db.run(move |c| {
+            c.transaction(|| {
+                if update_set.is_default {
+                    clear_default_template(c)?;
+                }
+                diesel::update(database::report_templates::table.find(template_id))
+                    .set(update_set)
+                    .get_result(c)
+            })
+        })
+        .await?,
+    )
+}
+
+#[post("/remove_template", data = "<template_id_data>")]
+pub async fn remove_report_template(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    template_id_data: Json<ClientReportTemplateIdData>,
+) -> GenericResult<String> {
+    let template_id = template_id_data.template_id;
+    match db
+        .run(move |c| {
+            diesel::delete(database::report_templates::table.find(template_id)).execute(c)
+        })
+        .await?
+    {
+        1 => SuccessResponse::build("完成".to_string()),
+        _ => Err(GenericError::InvalidInputError),
+    }
+}
+
+#[get("/get_templates")]
+pub async fn get_report_templates(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+) -> GenericResult<Vec<ReportTemplate>> {
+    SuccessResponse::build(
+        db.run(|c| {
+            database::report_templates::table
+                .order(database::report_templates::id)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
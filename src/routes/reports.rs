@@ -1,90 +1,749 @@
-use crate::auth::StaffAuth;
-use crate::auxiliary::{GenericError, GenericResult, ProductBarcode, SuccessResponse, UuidWrapper};
+use crate::auth::{AdminAuth, ReportReviewerAuth, StaffAuth, UserDigest};
+use crate::auxiliary::{
+    build_report_share_link, inspect_report_file, is_report_link_expired,
+    is_report_signing_enabled, is_valid_report_key, mask_id_card_number, mask_name,
+    render_report_pdf, report_download_path, report_verification_code, report_verification_url,
+    sign_report_file, verify_report_link, verify_report_signature, GenericError, GenericResult,
+    ProductBarcode, RateLimit, ReportRenderData, ReportStorage, ReportStorageState,
+    SuccessResponse, UuidWrapper, REPORT_LINK_MAX_TTL_SECONDS, REPORT_LINK_TTL_SECONDS,
+    REPORT_MAX_SIZE,
+};
 use crate::database::{self, MainDatabaseConnection};
+use crate::jobs::check_report_storage;
 use crate::models::*;
+use crate::notifications::{emit_product_events, ProductEvent};
 
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::PgConnection;
 
 use chrono::prelude::*;
+use chrono::Duration;
 
-use rocket::data::ToByteUnit;
-use rocket::Data;
+use rocket::data::Capped;
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::http::{ContentType, Header};
+use rocket::request::FromParam;
+use rocket::response::Redirect;
+use rocket::tokio::fs;
+use rocket::{Data, State};
 
 use rocket::serde::json::Json;
 use uuid::Uuid;
 
-use lazy_static;
+use data_encoding::HEXLOWER;
 
-use std::env;
+use sha2::{Digest, Sha256};
 
-lazy_static! {
-    static ref DOWNLOAD_URL_BASE: String =
-        env::var("DOWNLOAD_URL_BASE").expect("未设置DOWNLOAD_URL_BASE");
+use std::net::IpAddr;
+
+#[derive(Responder)]
+pub enum ReportFile {
+    File((ContentType, Vec<u8>), Header<'static>),
+    Redirect(Redirect),
+}
+
+async fn open_report_file(
+    storage: &dyn ReportStorage,
+    report: &Report,
+) -> Result<ReportFile, GenericError> {
+    match &report.filename {
+        Some(filename) => {
+            let content = storage
+                .get(filename)
+                .await?
+                .ok_or(GenericError::ReportFileNotFoundError)?;
+            Ok(ReportFile::File(
+                (ContentType::PDF, content),
+                Header::new(
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", filename),
+                ),
+            ))
+        }
+        // Reports published with only an external URL can't be served from here
+        None => Ok(ReportFile::Redirect(Redirect::to(
+            report.download_url.to_owned(),
+        ))),
+    }
+}
+
+/// Staff can access every report, users only the current reports of their own products.
+fn find_accessible_report(
+    c: &PgConnection,
+    report_id: Uuid,
+    user_id: i32,
+    user_role: RoleEnum,
+) -> Result<Report, GenericError> {
+    let report: Report = database::reports::table.find(report_id).get_result(c)?;
+    if user_role != RoleEnum::User {
+        return Ok(report);
+    }
+    let product_id = match (report.is_current, report.product_id) {
+        (true, Some(product_id)) => product_id,
+        _ => return Err(GenericError::PermissionDeniedError),
+    };
+    let owned_count: i64 = database::products::table
+        .inner_join(database::profiles::table)
+        .filter(database::products::id.eq(product_id))
+        .filter(database::profiles::user_id.eq(user_id))
+        .count()
+        .get_result(c)?;
+    if owned_count > 0 {
+        Ok(report)
+    } else {
+        Err(GenericError::PermissionDeniedError)
+    }
+}
+
+fn record_report_download(
+    c: &PgConnection,
+    report_id: Uuid,
+    user_id: Option<i32>,
+    shared_by: Option<i32>,
+    client_ip: Option<IpAddr>,
+) -> QueryResult<usize> {
+    diesel::insert_into(database::report_downloads::table)
+        .values(NewReportDownload {
+            report_id,
+            user_id,
+            shared_by,
+            client_ip: client_ip.map(|client_ip| client_ip.to_string()),
+            download_time: Utc::now().naive_utc(),
+        })
+        .execute(c)
+}
+
+/// All reports of one test of a product, reports of kits without a kit type have no test type.
+fn reports_of_test(
+    product_id: i32,
+    test_type_id: Option<i32>,
+) -> database::reports::BoxedQuery<'static, Pg> {
+    let query = database::reports::table
+        .filter(database::reports::product_id.eq(product_id))
+        .into_boxed();
+    match test_type_id {
+        Some(test_type_id) => query.filter(database::reports::test_type_id.eq(test_type_id)),
+        None => query.filter(database::reports::test_type_id.is_null()),
+    }
+}
+
+fn expected_test_type_ids(c: &PgConnection, product: &Product) -> QueryResult<Vec<i32>> {
+    match product.kit_type_id {
+        Some(kit_type_id) => database::kit_type_tests::table
+            .filter(database::kit_type_tests::kit_type_id.eq(kit_type_id))
+            .select(database::kit_type_tests::test_type_id)
+            .get_results(c),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// A report of a kit with a kit type must be for one of the tests the kit type expects.
+fn resolve_test_type(
+    c: &PgConnection,
+    product: &Product,
+    test_type: Option<String>,
+) -> Result<Option<i32>, GenericError> {
+    let test_type_id = match test_type {
+        Some(test_type) => Some(
+            database::test_types::table
+                .filter(database::test_types::code.eq(test_type))
+                .select(database::test_types::id)
+                .get_result(c)
+                .optional()?
+                .ok_or(GenericError::TestTypeNotFoundError)?,
+        ),
+        None => None,
+    };
+    let expected_test_type_ids = expected_test_type_ids(c, product)?;
+    if !expected_test_type_ids.is_empty()
+        && !matches!(test_type_id, Some(test_type_id) if expected_test_type_ids.contains(&test_type_id))
+    {
+        return Err(GenericError::ReportTestTypeNotExpectedError);
+    }
+    Ok(test_type_id)
+}
+
+/// Finishes the product once every test its kit type expects has a current report, or once it
+/// has any current report without a kit type, and takes it back to Sampled otherwise. Emits the
+/// events in the same transaction.
+fn refresh_product_stage(
+    c: &PgConnection,
+    product: &Product,
+    event: ProductEvent,
+) -> QueryResult<()> {
+    let current_test_type_ids: Vec<Option<i32>> = database::reports::table
+        .filter(database::reports::product_id.eq(product.id))
+        .filter(database::reports::is_current.eq(true))
+        .select(database::reports::test_type_id)
+        .get_results(c)?;
+    let expected_test_type_ids = expected_test_type_ids(c, product)?;
+    let complete = match expected_test_type_ids.is_empty() {
+        true => !current_test_type_ids.is_empty(),
+        false => expected_test_type_ids
+            .iter()
+            .all(|test_type_id| current_test_type_ids.contains(&Some(*test_type_id))),
+    };
+    let current_stage = match (complete, product.current_stage) {
+        (true, _) => StageEnum::Finished,
+        (false, StageEnum::Finished) => StageEnum::Sampled,
+        (false, current_stage) => current_stage,
+    };
+    let mut events = Vec::new();
+    if current_stage != product.current_stage {
+        diesel::update(database::products::table.find(product.id))
+            .set(database::products::current_stage.eq(current_stage))
+            .execute(c)?;
+        events.push(ProductEvent::StageChanged);
+    }
+    events.push(event);
+    emit_product_events(c, &product.product_barcode, &events)
+}
+
+/// Inserts the report as the next version of its test for the product, waiting for review.
+/// Replacing a current report is an amendment and needs a reason.
+/// Rejected uploads still take up a version number, but only replacing a released version
+/// makes a report amended.
+fn next_report_version(earlier_versions: &[(i32, ReportReviewStatusEnum)]) -> (i32, bool) {
+    let version = earlier_versions
+        .iter()
+        .map(|(version, _)| *version)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let amended = earlier_versions
+        .iter()
+        .any(|(_, review_status)| *review_status == ReportReviewStatusEnum::Approved);
+    (version, amended)
+}
+
+async fn submit_report_for_review(
+    db: &MainDatabaseConnection,
+    mut new_report: NewReport,
+    product_barcode: String,
+    test_type: Option<String>,
+) -> GenericResult<String> {
+    db.run(move |c| {
+        c.transaction(|| {
+            let product: Product = database::products::table
+                .filter(database::products::product_barcode.eq_all(&product_barcode))
+                .for_update()
+                .get_result(c)?;
+            let test_type_id = resolve_test_type(c, &product, test_type)?;
+            let current_count: i64 = reports_of_test(product.id, test_type_id)
+                .filter(database::reports::is_current.eq(true))
+                .count()
+                .get_result(c)?;
+            if current_count > 0 && new_report.amendment_reason.is_none() {
+                return Err(GenericError::ReportAmendmentReasonRequiredError);
+            }
+            let pending_count: i64 = reports_of_test(product.id, test_type_id)
+                .filter(database::reports::review_status.eq(ReportReviewStatusEnum::PendingReview))
+                .filter(database::reports::retracted_time.is_null())
+                .count()
+                .get_result(c)?;
+            if pending_count > 0 {
+                return Err(GenericError::ReportPendingReviewError);
+            }
+            let earlier_versions: Vec<(i32, ReportReviewStatusEnum)> =
+                reports_of_test(product.id, test_type_id)
+                    .select((database::reports::version, database::reports::review_status))
+                    .get_results(c)?;
+            let (version, amended) = next_report_version(&earlier_versions);
+            new_report.product_id = Some(product.id);
+            new_report.test_type_id = test_type_id;
+            new_report.version = version;
+            new_report.amended = amended;
+            new_report.review_status = ReportReviewStatusEnum::PendingReview;
+            diesel::insert_into(database::reports::table)
+                .values(new_report)
+                .execute(c)?;
+            Ok(())
+        })
+    })
+    .await?;
+    SuccessResponse::build("成功".to_string())
+}
+
+fn check_metadata_field(
+    value: Option<String>,
+    max_length: usize,
+) -> Result<Option<String>, GenericError> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) if value.chars().count() > max_length => Err(GenericError::InvalidInputError),
+        Some(value) => Ok(Some(value.to_string())),
+    }
+}
+
+fn check_report_metadata(metadata: ReportMetadata) -> Result<ReportMetadata, GenericError> {
+    Ok(ReportMetadata {
+        original_filename: check_metadata_field(metadata.original_filename, 255)?,
+        result_summary: check_metadata_field(metadata.result_summary, 2000)?,
+        result_category: check_metadata_field(metadata.result_category, 32)?,
+        technician_comments: check_metadata_field(metadata.technician_comments, 2000)?,
+        lab_accession_number: check_metadata_field(metadata.lab_accession_number, 64)?,
+        amendment_reason: check_metadata_field(metadata.amendment_reason, 500)?,
+        test_type: check_metadata_field(metadata.test_type, 32)?,
+        template_id: metadata.template_id,
+        result_items: metadata.result_items,
+    })
 }
 
-#[post("/upload_report/<product_barcode>", data = "<raw_data>")]
+async fn save_uploaded_report(
+    db: &MainDatabaseConnection,
+    storage: &dyn ReportStorage,
+    report_id: Uuid,
+    uploader_id: i32,
+    product_barcode: String,
+    content: Vec<u8>,
+    metadata: ReportMetadata,
+) -> GenericResult<String> {
+    let metadata = check_report_metadata(metadata)?;
+    let file_info = inspect_report_file(&content)?;
+    let signature = sign_report_file(&content)?;
+    let filename = format!("{}.pdf", report_id);
+    storage.put(&filename, content).await?;
+    let new_report = NewReport {
+        id: report_id,
+        download_url: report_download_path(report_id),
+        filename: Some(filename.to_owned()),
+        upload_time: Utc::now().naive_utc(),
+        uploader_id,
+        sha256: Some(file_info.sha256),
+        file_size: Some(file_info.file_size),
+        page_count: Some(file_info.page_count),
+        original_filename: metadata.original_filename,
+        result_summary: metadata.result_summary,
+        technician_comments: metadata.technician_comments,
+        lab_accession_number: metadata.lab_accession_number,
+        product_id: None,
+        version: 1,
+        amendment_reason: metadata.amendment_reason,
+        review_status: ReportReviewStatusEnum::PendingReview,
+        template_id: metadata.template_id,
+        result_items: metadata.result_items,
+        verification_code: report_verification_code(report_id),
+        result_category: metadata.result_category,
+        signature,
+        test_type_id: None,
+        is_current: false,
+        amended: false,
+    };
+    let result =
+        submit_report_for_review(db, new_report, product_barcode, metadata.test_type).await;
+    // Nothing refers to the file when the report couldn't be submitted
+    if result.is_err() {
+        if let Err(error) = storage.delete(&filename).await {
+            warn!("未能删除未使用的报告文件{}：{:?}", filename, error);
+        }
+    }
+    result
+}
+
+#[post(
+    "/upload_report/<product_barcode>?<amendment_reason>&<test_type>",
+    data = "<raw_data>",
+    rank = 2
+)]
 pub async fn upload_report(
     db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
     raw_data: Data<'_>,
     staff: StaffAuth,
     product_barcode: ProductBarcode<'_>,
+    amendment_reason: Option<String>,
+    test_type: Option<String>,
 ) -> GenericResult<String> {
-    let filename = format!("{}.pdf", Uuid::new_v4());
-    let base = env::var("REPORT_PATH").expect("未设置REPORT_PATH");
-    let mut full_path = env::current_dir().expect("工作路径获取失败");
-    full_path.push(base);
-    full_path.push(&filename);
-    let download_url = (*DOWNLOAD_URL_BASE).to_string() + &filename;
-    raw_data
-        .open(20.megabytes())
-        .into_file(full_path)
+    let content = raw_data
+        .open(*REPORT_MAX_SIZE)
+        .into_bytes()
         .await
         .map_err(|_| GenericError::ServerInternalError)?;
-    let current_timestamp = Utc::now().naive_utc();
-    let new_report = NewReport {
-        download_url: download_url.to_owned(),
-        filename: Some(filename),
-        upload_time: current_timestamp,
-        uploader_id: staff.user_id,
-    };
-    let insert_result: Report = db
+    if !content.is_complete() {
+        return Err(GenericError::ReportFileTooLargeError);
+    }
+    save_uploaded_report(
+        &db,
+        storage_state.storage.as_ref(),
+        Uuid::new_v4(),
+        staff.user_id,
+        product_barcode.inner().to_owned(),
+        content.into_inner(),
+        ReportMetadata {
+            amendment_reason,
+            test_type,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+async fn read_form_file(file: &Capped<TempFile<'_>>) -> Result<Vec<u8>, GenericError> {
+    if !file.is_complete() {
+        return Err(GenericError::ReportFileTooLargeError);
+    }
+    // A plain text field is buffered in memory and has no path
+    let path = file.path().ok_or(GenericError::InvalidInputError)?;
+    fs::read(path)
+        .await
+        .map_err(|_| GenericError::ServerInternalError)
+}
+
+#[post(
+    "/upload_report/<product_barcode>",
+    format = "multipart/form-data",
+    data = "<upload_form>",
+    rank = 1
+)]
+pub async fn upload_report_form(
+    db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
+    upload_form: Form<ReportUploadForm<'_>>,
+    staff: StaffAuth,
+    product_barcode: ProductBarcode<'_>,
+) -> GenericResult<String> {
+    let upload_form = upload_form.into_inner();
+    let content = read_form_file(&upload_form.file).await?;
+    let file = &upload_form.file;
+    let original_filename = upload_form.original_filename.or_else(|| {
+        file.raw_name()
+            .map(|name| name.dangerous_unsafe_unsanitized_raw().to_string())
+    });
+    save_uploaded_report(
+        &db,
+        storage_state.storage.as_ref(),
+        Uuid::new_v4(),
+        staff.user_id,
+        product_barcode.inner().to_owned(),
+        content,
+        ReportMetadata {
+            original_filename,
+            result_summary: upload_form.result_summary,
+            result_category: upload_form.result_category,
+            technician_comments: upload_form.technician_comments,
+            lab_accession_number: upload_form.lab_accession_number,
+            amendment_reason: upload_form.amendment_reason,
+            test_type: upload_form.test_type,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+fn format_report_date(time: Option<NaiveDateTime>, format: &str) -> String {
+    time.map(|time| time.format(format).to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Renders a report from structured results with a template, then stores and submits it
+/// for review just like an uploaded file.
+#[post("/generate_report", data = "<generate_report_data>")]
+pub async fn generate_report(
+    db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
+    staff: StaffAuth,
+    generate_report_data: Json<ClientGenerateReportData>,
+) -> GenericResult<String> {
+    let generate_report_data = generate_report_data.into_inner();
+    if generate_report_data.results.is_empty()
+        || generate_report_data.results.len() > 100
+        || generate_report_data
+            .results
+            .iter()
+            .any(|result| result.item.trim().is_empty() || result.result.trim().is_empty())
+    {
+        return Err(GenericError::InvalidInputError);
+    }
+    let metadata = check_report_metadata(ReportMetadata {
+        result_summary: generate_report_data.result_summary,
+        result_category: generate_report_data.result_category,
+        technician_comments: generate_report_data.technician_comments,
+        lab_accession_number: generate_report_data.lab_accession_number,
+        amendment_reason: generate_report_data.amendment_reason,
+        test_type: generate_report_data.test_type,
+        ..Default::default()
+    })?;
+    let product_barcode = generate_report_data.product_barcode;
+    let template_id = generate_report_data.template_id;
+    let query_barcode = product_barcode.to_owned();
+    let (profile, template): (Option<Profile>, Option<ReportTemplate>) = db
         .run(move |c| {
-            diesel::insert_into(database::reports::table)
-                .values(new_report)
+            let profile: Option<Profile> = database::products::table
+                .inner_join(database::profiles::table)
+                .filter(database::products::product_barcode.eq(query_barcode))
+                .select(database::profiles::all_columns)
                 .get_result(c)
+                .optional()?;
+            let template: Option<ReportTemplate> = match template_id {
+                Some(template_id) => database::report_templates::table
+                    .find(template_id)
+                    .get_result(c)
+                    .optional()?,
+                None => database::report_templates::table
+                    .filter(database::report_templates::is_default.eq(true))
+                    .get_result(c)
+                    .optional()?,
+            };
+            Ok::<_, diesel::result::Error>((profile, template))
         })
         .await?;
-    let input_barcode = product_barcode.inner().to_owned();
-    match db
-        .run(move |c| {
-            diesel::update(
-                database::products::table
-                    .filter(database::products::product_barcode.eq_all(input_barcode)),
-            )
-            .set((
-                database::products::current_stage.eq_all(StageEnum::Finished),
-                database::products::report_id.eq_all(insert_result.id),
-            ))
-            .execute(c)
-        })
-        .await?
-    {
-        1 => SuccessResponse::build("成功".to_string()),
-        _ => Err(GenericError::ServerInternalError),
+    let profile = profile.ok_or(GenericError::ProfileNotExistError)?;
+    let template = template.ok_or(GenericError::ReportTemplateNotFoundError)?;
+    let report_id = Uuid::new_v4();
+    let render_data = ReportRenderData {
+        name: profile.name,
+        id_card_number: mask_id_card_number(&profile.id_card_number),
+        birth_date: format_report_date(Some(profile.birth_date), "%Y-%m-%d"),
+        product_barcode: product_barcode.to_owned(),
+        sample_time: format_report_date(profile.sample_time, "%Y-%m-%d %H:%M"),
+        report_time: format_report_date(Some(Utc::now().naive_utc()), "%Y-%m-%d %H:%M"),
+        result_summary: metadata.result_summary.to_owned().unwrap_or_default(),
+        technician_comments: metadata.technician_comments.to_owned().unwrap_or_default(),
+        lab_accession_number: metadata.lab_accession_number.to_owned().unwrap_or_default(),
+        results: generate_report_data.results,
+        verification_url: report_verification_url(&report_verification_code(report_id)),
+    };
+    let content = render_report_pdf(&template, &render_data)?;
+    save_uploaded_report(
+        &db,
+        storage_state.storage.as_ref(),
+        report_id,
+        staff.user_id,
+        product_barcode,
+        content,
+        ReportMetadata {
+            template_id: Some(template.id),
+            result_items: serde_json::to_string(&render_data.results).ok(),
+            ..metadata
+        },
+    )
+    .await
+}
+
+fn parse_timestamp_millis(timestamp: i64) -> Result<NaiveDateTime, GenericError> {
+    NaiveDateTime::from_timestamp_opt(timestamp / 1000, 0).ok_or(GenericError::InvalidInputError)
+}
+
+fn filter_reports(
+    c: &PgConnection,
+    page: i32,
+    filter: ReportListFilter,
+) -> Result<Vec<Report>, GenericError> {
+    let mut query = database::reports::table
+        .left_join(
+            database::products::table
+                .on(database::reports::product_id.eq(database::products::id.nullable())),
+        )
+        .select(database::reports::all_columns)
+        .into_boxed();
+    if let Some(uploader_id) = filter.uploader_id {
+        query = query.filter(database::reports::uploader_id.eq(uploader_id));
     }
+    if let Some(from) = filter.from {
+        query = query.filter(database::reports::upload_time.ge(parse_timestamp_millis(from)?));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(database::reports::upload_time.lt(parse_timestamp_millis(to)?));
+    }
+    if let Some(stage) = filter.stage {
+        query = query.filter(database::products::current_stage.eq(StageEnum::from_param(&stage)?));
+    }
+    if let Some(review_status) = filter.review_status {
+        query = query.filter(
+            database::reports::review_status
+                .eq(ReportReviewStatusEnum::from_param(&review_status)?),
+        );
+    }
+    Ok(query
+        .order(database::reports::upload_time.desc())
+        .limit(10)
+        .offset((page * 10) as i64)
+        .get_results(c)?)
 }
 
 #[get("/get_reports/<page>/<uploader_id>")]
 pub async fn get_filtered_reports(
     db: MainDatabaseConnection,
+    _staff: StaffAuth,
     page: i32,
     uploader_id: i32,
+) -> GenericResult<Vec<Report>> {
+    let filter = ReportListFilter {
+        uploader_id: Some(uploader_id),
+        ..Default::default()
+    };
+    SuccessResponse::build(db.run(move |c| filter_reports(c, page, filter)).await?)
+}
+
+#[get("/get_reports/<page>?<filter..>")]
+pub async fn get_reports(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    page: i32,
+    filter: ReportListFilter,
+) -> GenericResult<Vec<Report>> {
+    SuccessResponse::build(db.run(move |c| filter_reports(c, page, filter)).await?)
+}
+
+/// Current reports with their products and tests, of one user or one product or both.
+fn load_current_reports(
+    c: &PgConnection,
+    user_id: Option<i32>,
+    product_barcode: Option<String>,
+    page: Option<i32>,
+) -> QueryResult<Vec<UserReport>> {
+    let mut query = database::reports::table
+        .inner_join(database::products::table.inner_join(database::profiles::table))
+        .left_join(database::test_types::table)
+        .filter(database::reports::is_current.eq(true))
+        .select((
+            database::reports::all_columns,
+            database::products::product_barcode,
+            database::profiles::sample_time,
+            database::test_types::all_columns.nullable(),
+        ))
+        .order(database::reports::upload_time.desc())
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(database::profiles::user_id.eq(user_id));
+    }
+    if let Some(product_barcode) = product_barcode {
+        query = query.filter(database::products::product_barcode.eq(product_barcode));
+    }
+    if let Some(page) = page {
+        query = query.limit(10).offset((page * 10) as i64);
+    }
+    let reports: Vec<(Report, String, Option<NaiveDateTime>, Option<TestType>)> =
+        query.get_results(c)?;
+    Ok(reports
+        .into_iter()
+        .map(
+            |(report, product_barcode, sample_time, test_type)| UserReport {
+                report: report.into(),
+                product_barcode,
+                sample_time,
+                test_type,
+            },
+        )
+        .collect())
+}
+
+/// The current reports of every product bound to one of the user's profiles.
+#[get("/my_reports/<page>")]
+pub async fn get_my_reports(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    page: i32,
+) -> GenericResult<Vec<UserReport>> {
+    let user_id = user_digest.user_id;
+    SuccessResponse::build(
+        db.run(move |c| load_current_reports(c, Some(user_id), None, Some(page)))
+            .await?,
+    )
+}
+
+/// One current report for each test of the kit, users only see their own kits.
+#[get("/get_product_reports/<product_barcode>")]
+pub async fn get_product_reports(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    product_barcode: ProductBarcode<'_>,
+) -> GenericResult<Vec<UserReport>> {
+    let user_id = match user_digest.user_role {
+        RoleEnum::User => Some(user_digest.user_id),
+        _ => None,
+    };
+    let product_barcode = product_barcode.inner().to_owned();
+    SuccessResponse::build(
+        db.run(move |c| load_current_reports(c, user_id, Some(product_barcode), None))
+            .await?,
+    )
+}
+
+/// Approval releases the report to the customer as the current one of its test, and finishes the
+/// product once every test of the kit is released. The uploader can't review their own report,
+/// and a rejection needs comments.
+#[post("/review_report", data = "<review_report_data>")]
+pub async fn review_report(
+    db: MainDatabaseConnection,
+    review_report_data: Json<ClientReviewReportData>,
+    reviewer: ReportReviewerAuth,
+) -> GenericResult<String> {
+    let report_id = review_report_data.report_id;
+    let approved = review_report_data.approved;
+    let review_comments = check_metadata_field(review_report_data.comments.to_owned(), 2000)?;
+    if !approved && review_comments.is_none() {
+        return Err(GenericError::InvalidInputError);
+    }
+    db.run(move |c| {
+        c.transaction(|| {
+            let report: Report = database::reports::table
+                .find(report_id)
+                .for_update()
+                .get_result(c)?;
+            if report.review_status != ReportReviewStatusEnum::PendingReview
+                || report.retracted_time.is_some()
+            {
+                return Err(GenericError::ReportNotPendingReviewError);
+            }
+            if report.uploader_id == reviewer.user_id {
+                return Err(GenericError::ReportSelfReviewError);
+            }
+            let review_status = match approved {
+                true => ReportReviewStatusEnum::Approved,
+                false => ReportReviewStatusEnum::Rejected,
+            };
+            diesel::update(database::reports::table.find(report_id))
+                .set((
+                    database::reports::review_status.eq(review_status),
+                    database::reports::reviewed_by.eq(Some(reviewer.user_id)),
+                    database::reports::reviewed_time.eq(Some(Utc::now().naive_utc())),
+                    database::reports::review_comments.eq(review_comments),
+                ))
+                .execute(c)?;
+            let product_id = match (approved, report.product_id) {
+                (true, Some(product_id)) => product_id,
+                _ => return Ok(()),
+            };
+            let product: Product = database::products::table
+                .find(product_id)
+                .for_update()
+                .get_result(c)?;
+            // The approved report replaces the current one of the same test
+            let previous_report_ids: Vec<Uuid> = reports_of_test(product_id, report.test_type_id)
+                .filter(database::reports::is_current.eq(true))
+                .select(database::reports::id)
+                .get_results(c)?;
+            diesel::update(
+                database::reports::table.filter(database::reports::id.eq_any(previous_report_ids)),
+            )
+            .set(database::reports::is_current.eq(false))
+            .execute(c)?;
+            diesel::update(database::reports::table.find(report_id))
+                .set(database::reports::is_current.eq(true))
+                .execute(c)?;
+            refresh_product_stage(c, &product, ProductEvent::ReportAttached)?;
+            Ok(())
+        })
+    })
+    .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
+#[get("/get_pending_reviews/<page>")]
+pub async fn get_pending_reviews(
+    db: MainDatabaseConnection,
+    _reviewer: ReportReviewerAuth,
+    page: i32,
 ) -> GenericResult<Vec<Report>> {
     SuccessResponse::build(
         db.run(move |c| {
             database::reports::table
-                .filter(database::reports::uploader_id.eq_all(uploader_id))
-                .order(database::reports::id)
+                .filter(database::reports::review_status.eq(ReportReviewStatusEnum::PendingReview))
+                .filter(database::reports::retracted_time.is_null())
+                .order(database::reports::upload_time)
                 .limit(10)
                 .offset((page * 10) as i64)
                 .get_results(c)
@@ -93,101 +752,486 @@ pub async fn get_filtered_reports(
     )
 }
 
-#[get("/get_reports/<page>")]
-pub async fn get_reports(db: MainDatabaseConnection, page: i32) -> GenericResult<Vec<Report>> {
+/// Retracted reports are kept for the record. If it was the current report of its test, the
+/// latest remaining approved version of the test takes its place, and the product goes back to
+/// Sampled when a test is left without a report.
+#[post("/retract_report", data = "<retract_report_data>")]
+pub async fn retract_report(
+    db: MainDatabaseConnection,
+    retract_report_data: Json<ClientRetractReportData>,
+    staff: StaffAuth,
+) -> GenericResult<String> {
+    let report_id = retract_report_data.report_id;
+    let retraction_reason = check_metadata_field(Some(retract_report_data.reason.to_owned()), 500)?
+        .ok_or(GenericError::InvalidInputError)?;
+    db.run(move |c| {
+        c.transaction(|| {
+            let report: Report = database::reports::table
+                .find(report_id)
+                .for_update()
+                .get_result(c)?;
+            if report.retracted_time.is_some() {
+                return Err(GenericError::ReportRetractedError);
+            }
+            diesel::update(database::reports::table.find(report_id))
+                .set((
+                    database::reports::retracted_time.eq(Some(Utc::now().naive_utc())),
+                    database::reports::retracted_by.eq(Some(staff.user_id)),
+                    database::reports::retraction_reason.eq(Some(retraction_reason)),
+                    database::reports::is_current.eq(false),
+                ))
+                .execute(c)?;
+            let product_id = match (report.is_current, report.product_id) {
+                (true, Some(product_id)) => product_id,
+                _ => return Ok(()),
+            };
+            let product: Product = database::products::table
+                .find(product_id)
+                .for_update()
+                .get_result(c)?;
+            let previous_report_id: Option<Uuid> = reports_of_test(product_id, report.test_type_id)
+                .filter(database::reports::retracted_time.is_null())
+                .filter(database::reports::review_status.eq(ReportReviewStatusEnum::Approved))
+                .order(database::reports::version.desc())
+                .select(database::reports::id)
+                .first(c)
+                .optional()?;
+            if let Some(previous_report_id) = previous_report_id {
+                diesel::update(database::reports::table.find(previous_report_id))
+                    .set(database::reports::is_current.eq(true))
+                    .execute(c)?;
+            }
+            refresh_product_stage(c, &product, ProductEvent::ReportRetracted)?;
+            Ok(())
+        })
+    })
+    .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
+#[get("/get_report_versions/<product_barcode>")]
+pub async fn get_report_versions(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    product_barcode: ProductBarcode<'_>,
+) -> GenericResult<Vec<Report>> {
+    let product_barcode = product_barcode.inner().to_owned();
     SuccessResponse::build(
         db.run(move |c| {
+            let product_id: i32 = database::products::table
+                .filter(database::products::product_barcode.eq(product_barcode))
+                .select(database::products::id)
+                .get_result(c)?;
             database::reports::table
-                .order(database::reports::id)
-                .limit(10)
-                .offset((page * 10) as i64)
+                .filter(database::reports::product_id.eq(product_id))
+                .order((database::reports::test_type_id, database::reports::version))
                 .get_results(c)
         })
         .await?,
     )
 }
 
-#[post("/remove_report", data = "<remove_report_data>")]
-pub async fn remove_report(
+#[get("/get_report/<report_id>")]
+pub async fn get_report(
     db: MainDatabaseConnection,
-    remove_report_data: Json<ClientRemoveReportData>,
-    _staff: StaffAuth,
-) -> GenericResult<String> {
-    match db
+    user_digest: UserDigest,
+    report_id: UuidWrapper,
+) -> GenericResult<ReportSummary> {
+    let report_id: Uuid = report_id.into();
+    let (user_id, user_role) = (user_digest.user_id, user_digest.user_role);
+    let report = db
+        .run(move |c| find_accessible_report(c, report_id, user_id, user_role))
+        .await?;
+    SuccessResponse::build(report.into())
+}
+
+#[get("/download/<report_id>")]
+pub async fn download_report(
+    db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
+    user_digest: UserDigest,
+    client_ip: Option<IpAddr>,
+    report_id: UuidWrapper,
+) -> Result<ReportFile, GenericError> {
+    let report_id: Uuid = report_id.into();
+    let (user_id, user_role) = (user_digest.user_id, user_digest.user_role);
+    let report = db
+        .run(move |c| find_accessible_report(c, report_id, user_id, user_role))
+        .await?;
+    let report_file = open_report_file(storage_state.storage.as_ref(), &report).await?;
+    db.run(move |c| record_report_download(c, report_id, Some(user_id), None, client_ip))
+        .await?;
+    Ok(report_file)
+}
+
+#[post("/create_share_link", data = "<share_report_data>")]
+pub async fn create_report_share_link(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    share_report_data: Json<ClientShareReportData>,
+) -> GenericResult<ReportShareLink> {
+    let expires_in_seconds = share_report_data
+        .expires_in_seconds
+        .unwrap_or(*REPORT_LINK_TTL_SECONDS);
+    if expires_in_seconds <= 0 || expires_in_seconds > *REPORT_LINK_MAX_TTL_SECONDS {
+        return Err(GenericError::InvalidInputError);
+    }
+    let report_id = share_report_data.report_id;
+    let (user_id, user_role) = (user_digest.user_id, user_digest.user_role);
+    db.run(move |c| find_accessible_report(c, report_id, user_id, user_role))
+        .await?;
+    let expiration_time = Utc::now() + Duration::seconds(expires_in_seconds);
+    SuccessResponse::build(ReportShareLink {
+        url: build_report_share_link(report_id, expiration_time.timestamp(), user_id),
+        expiration_time: expiration_time.naive_utc(),
+    })
+}
+
+#[get("/shared/<report_id>?<expires>&<by>&<signature>")]
+pub async fn download_shared_report(
+    db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
+    client_ip: Option<IpAddr>,
+    report_id: UuidWrapper,
+    expires: i64,
+    by: i32,
+    signature: String,
+) -> Result<ReportFile, GenericError> {
+    let report_id: Uuid = report_id.into();
+    if !verify_report_link(report_id, expires, by, &signature) {
+        return Err(GenericError::ReportLinkInvalidError);
+    }
+    if is_report_link_expired(expires) {
+        return Err(GenericError::ReportLinkExpiredError);
+    }
+    // The sharer may have lost access since, e.g. their account was merged away or the kit
+    // changed hands
+    let report: Report = db
         .run(move |c| {
-            diesel::delete(database::reports::table.find(remove_report_data.report_id)).execute(c)
+            let sharer_role: Option<RoleEnum> = database::users::table
+                .find(by)
+                .select(database::users::user_role)
+                .get_result(c)
+                .optional()?;
+            let sharer_role = sharer_role.ok_or(GenericError::ReportNoLongerValidError)?;
+            match find_accessible_report(c, report_id, by, sharer_role) {
+                Err(GenericError::PermissionDeniedError) => {
+                    Err(GenericError::ReportNoLongerValidError)
+                }
+                result => result,
+            }
         })
-        .await?
+        .await?;
+    // A link shared before an amendment or retraction must not keep serving the old file
+    if report.retracted_time.is_some() || (report.product_id.is_some() && !report.is_current) {
+        return Err(GenericError::ReportNoLongerValidError);
+    }
+    let report_file = open_report_file(storage_state.storage.as_ref(), &report).await?;
+    db.run(move |c| record_report_download(c, report_id, None, Some(by), client_ip))
+        .await?;
+    Ok(report_file)
+}
+
+/// Public, so only approved reports are found and the response identifies nobody.
+#[get("/verify/<verification_code>")]
+pub async fn verify_report(
+    db: MainDatabaseConnection,
+    _rate_limit: RateLimit,
+    verification_code: String,
+) -> GenericResult<ReportVerification> {
+    let verification_code = verification_code.trim().to_uppercase();
+    if verification_code.is_empty()
+        || verification_code.len() > 32
+        || !verification_code.chars().all(|c| c.is_ascii_alphanumeric())
     {
-        1 => SuccessResponse::build("完成".to_string()),
-        _ => Err(GenericError::InvalidInputError),
+        return Err(GenericError::ReportVerificationCodeInvalidError);
     }
+    let found: Option<(Report, Option<String>)> = db
+        .run(move |c| {
+            database::reports::table
+                .left_join(
+                    database::products::table
+                        .on(database::reports::product_id.eq(database::products::id.nullable())),
+                )
+                .left_join(
+                    database::profiles::table
+                        .on(database::products::profile_id.eq(database::profiles::id.nullable())),
+                )
+                .filter(database::reports::verification_code.eq(verification_code))
+                .filter(database::reports::review_status.eq(ReportReviewStatusEnum::Approved))
+                .select((
+                    database::reports::all_columns,
+                    database::profiles::name.nullable(),
+                ))
+                .get_result(c)
+                .optional()
+        })
+        .await?;
+    let (report, name) = found.ok_or(GenericError::ReportVerificationCodeInvalidError)?;
+    let retracted = report.retracted_time.is_some();
+    SuccessResponse::build(ReportVerification {
+        issue_date: report.reviewed_time.unwrap_or(report.upload_time).date(),
+        result_category: report.result_category,
+        masked_name: name.as_deref().map(mask_name),
+        amended: report.amended,
+        superseded: !retracted && report.product_id.is_some() && !report.is_current,
+        retracted,
+    })
 }
 
-#[get("/get_report/<report_id>")]
-pub async fn get_report(
+#[get("/signature/<report_id>")]
+pub async fn download_report_signature(
     db: MainDatabaseConnection,
+    user_digest: UserDigest,
     report_id: UuidWrapper,
-) -> GenericResult<String> {
+) -> Result<ReportFile, GenericError> {
+    let report_id: Uuid = report_id.into();
+    let (user_id, user_role) = (user_digest.user_id, user_digest.user_role);
+    let report = db
+        .run(move |c| find_accessible_report(c, report_id, user_id, user_role))
+        .await?;
+    let signature = report
+        .signature
+        .ok_or(GenericError::ReportFileNotFoundError)?;
+    Ok(ReportFile::File(
+        (
+            ContentType::new("application", "pkcs7-signature"),
+            signature,
+        ),
+        Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.p7s\"", report_id),
+        ),
+    ))
+}
+
+async fn check_report_file_signature(
+    db: &MainDatabaseConnection,
+    content: Vec<u8>,
+    signature: Option<Vec<u8>>,
+) -> GenericResult<ReportSignatureCheck> {
+    if !is_report_signing_enabled() {
+        return Err(GenericError::ReportSigningNotConfiguredError);
+    }
+    let sha256 = HEXLOWER.encode(&Sha256::digest(&content));
+    let candidates: Vec<(String, Option<Vec<u8>>)> = db
+        .run(move |c| {
+            database::reports::table
+                .filter(database::reports::sha256.eq(sha256))
+                .filter(database::reports::signature.is_not_null())
+                .filter(database::reports::review_status.eq(ReportReviewStatusEnum::Approved))
+                .select((
+                    database::reports::verification_code,
+                    database::reports::signature,
+                ))
+                .get_results(c)
+        })
+        .await?;
+    // The verification code is only handed out for reports we released, so that its status
+    // can be looked up afterwards
+    let verification_code = candidates
+        .first()
+        .map(|(verification_code, _)| verification_code.to_owned());
+    if let Some(signature) = signature {
+        let valid = verify_report_signature(&content, &signature)?;
+        return SuccessResponse::build(ReportSignatureCheck {
+            valid,
+            verification_code: verification_code.filter(|_| valid),
+        });
+    }
+    for (verification_code, signature) in candidates {
+        if verify_report_signature(&content, &signature.unwrap_or_default())? {
+            return SuccessResponse::build(ReportSignatureCheck {
+                valid: true,
+                verification_code: Some(verification_code),
+            });
+        }
+    }
+    SuccessResponse::build(ReportSignatureCheck {
+        valid: false,
+        verification_code: None,
+    })
+}
+
+/// Checks a PDF someone was handed against the signatures of the reports we released.
+#[post("/verify_signature", data = "<raw_data>", rank = 2)]
+pub async fn verify_report_file_signature(
+    db: MainDatabaseConnection,
+    _rate_limit: RateLimit,
+    raw_data: Data<'_>,
+) -> GenericResult<ReportSignatureCheck> {
+    let content = raw_data
+        .open(*REPORT_MAX_SIZE)
+        .into_bytes()
+        .await
+        .map_err(|_| GenericError::ServerInternalError)?;
+    if !content.is_complete() {
+        return Err(GenericError::ReportFileTooLargeError);
+    }
+    check_report_file_signature(&db, content.into_inner(), None).await
+}
+
+/// Same as above, but a detached signature sent along with the PDF is checked against our
+/// certificate instead of the one we stored.
+#[post(
+    "/verify_signature",
+    format = "multipart/form-data",
+    data = "<signature_check_form>",
+    rank = 1
+)]
+pub async fn verify_report_file_signature_form(
+    db: MainDatabaseConnection,
+    _rate_limit: RateLimit,
+    signature_check_form: Form<ReportSignatureCheckForm<'_>>,
+) -> GenericResult<ReportSignatureCheck> {
+    let content = read_form_file(&signature_check_form.file).await?;
+    let signature = match &signature_check_form.signature {
+        Some(signature) => Some(read_form_file(signature).await?),
+        None => None,
+    };
+    check_report_file_signature(&db, content, signature).await
+}
+
+/// Lists orphan files and rows whose files are missing or changed, orphans are only deleted
+/// when asked to.
+#[post("/check_storage", data = "<check_storage_data>")]
+pub async fn check_storage(
+    db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
+    _admin: AdminAuth,
+    check_storage_data: Json<ClientCheckReportStorageData>,
+) -> GenericResult<ReportStorageCheck> {
+    SuccessResponse::build(
+        check_report_storage(
+            &db,
+            storage_state.storage.as_ref(),
+            check_storage_data.delete_orphans,
+            check_storage_data.verify_checksums,
+        )
+        .await?,
+    )
+}
+
+#[get("/get_downloads/<report_id>")]
+pub async fn get_report_downloads(
+    db: MainDatabaseConnection,
+    _staff: StaffAuth,
+    report_id: UuidWrapper,
+) -> GenericResult<Vec<ReportDownload>> {
+    let report_id: Uuid = report_id.into();
     SuccessResponse::build(
         db.run(move |c| {
-            database::reports::table
-                .find::<Uuid>(report_id.into())
-                .get_result::<Report>(c)
+            database::report_downloads::table
+                .filter(database::report_downloads::report_id.eq(report_id))
+                .order(database::report_downloads::id.desc())
+                .get_results(c)
         })
-        .await?
-        .download_url,
+        .await?,
     )
 }
 
 #[post("/publish_report", data = "<publish_report_data>")]
 pub async fn publish_report(
     db: MainDatabaseConnection,
+    storage_state: &State<ReportStorageState>,
     staff: StaffAuth,
     publish_report_data: Json<PublishReportData>,
 ) -> GenericResult<String> {
-    let download_url: String = publish_report_data
-        .download_url
-        .as_ref()
-        .unwrap_or(
-            &((*DOWNLOAD_URL_BASE).to_string()
-                + &publish_report_data
-                    .filename
-                    .as_ref()
-                    .ok_or(GenericError::InvalidInputError)?
-                    .to_string()),
-        )
-        .to_string();
+    let report_id = Uuid::new_v4();
+    // A stored file is always served through the download route, an external URL as is
+    let (download_url, file_info, signature) = match (
+        &publish_report_data.filename,
+        &publish_report_data.download_url,
+    ) {
+        (Some(filename), _) => {
+            if !is_valid_report_key(filename) {
+                return Err(GenericError::InvalidInputError);
+            }
+            let content = storage_state
+                .storage
+                .get(filename)
+                .await?
+                .ok_or(GenericError::ReportFileNotFoundError)?;
+            if content.len() as u64 > REPORT_MAX_SIZE.as_u64() {
+                return Err(GenericError::ReportFileTooLargeError);
+            }
+            (
+                report_download_path(report_id),
+                Some(inspect_report_file(&content)?),
+                sign_report_file(&content)?,
+            )
+        }
+        (None, Some(download_url)) => (download_url.to_owned(), None, None),
+        (None, None) => return Err(GenericError::InvalidInputError),
+    };
     let current_timestamp = Utc::now().naive_utc();
     let new_report = NewReport {
+        id: report_id,
         download_url,
         filename: publish_report_data.filename.to_owned(),
         upload_time: current_timestamp,
         uploader_id: staff.user_id,
+        sha256: file_info.as_ref().map(|info| info.sha256.to_owned()),
+        file_size: file_info.as_ref().map(|info| info.file_size),
+        page_count: file_info.as_ref().map(|info| info.page_count),
+        original_filename: None,
+        result_summary: None,
+        technician_comments: None,
+        lab_accession_number: None,
+        product_id: None,
+        version: 1,
+        amendment_reason: check_metadata_field(
+            publish_report_data.amendment_reason.to_owned(),
+            500,
+        )?,
+        review_status: ReportReviewStatusEnum::PendingReview,
+        template_id: None,
+        result_items: None,
+        verification_code: report_verification_code(report_id),
+        result_category: None,
+        signature,
+        test_type_id: None,
+        is_current: false,
+        amended: false,
     };
-    let insert_result: Report = db
-        .run(move |c| {
-            diesel::insert_into(database::reports::table)
-                .values(new_report)
-                .get_result(c)
-        })
-        .await?;
-    let input_barcode = publish_report_data.product_barcode.to_owned();
-    match db
-        .run(move |c| {
-            diesel::update(
-                database::products::table
-                    .filter(database::products::product_barcode.eq_all(input_barcode)),
-            )
-            .set((
-                database::products::current_stage.eq_all(StageEnum::Finished),
-                database::products::report_id.eq_all(insert_result.id),
-            ))
-            .execute(c)
-        })
-        .await?
-    {
-        1 => SuccessResponse::build("成功".to_string()),
-        _ => Err(GenericError::ServerInternalError),
+    submit_report_for_review(
+        &db,
+        new_report,
+        publish_report_data.product_barcode.to_owned(),
+        check_metadata_field(publish_report_data.test_type.to_owned(), 32)?,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ReportReviewStatusEnum::*;
+
+    #[test]
+    fn first_report_is_not_amended() {
+        assert_eq!(next_report_version(&[]), (1, false));
+    }
+
+    #[test]
+    fn rejected_versions_do_not_make_an_amendment() {
+        assert_eq!(next_report_version(&[(1, Rejected)]), (2, false));
+        assert_eq!(
+            next_report_version(&[(1, Rejected), (2, Rejected)]),
+            (3, false)
+        );
+    }
+
+    #[test]
+    fn replacing_an_approved_version_is_an_amendment() {
+        assert_eq!(
+            next_report_version(&[(1, Rejected), (2, Approved)]),
+            (3, true)
+        );
+        assert_eq!(
+            next_report_version(&[(2, Approved), (1, Rejected)]),
+            (3, true)
+        );
     }
 }
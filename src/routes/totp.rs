@@ -0,0 +1,396 @@
+use crate::auth::{
+    gen_recovery_codes, gen_totp_secret, gen_totp_uri, gen_totp_verified_token_cookie,
+    is_account_locked, normalize_recovery_code, record_login_failure, record_login_success,
+    verify_totp, AdminAuth, TotpPendingAuth, UserDigest, USER_AUTH_ARGON2_CONFIG, USER_AUTH_SALT,
+};
+use crate::auxiliary::{
+    gen_qrcode_svg, GenericError, GenericResult, RateLimit, RateLimiterState, SuccessResponse,
+};
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::*;
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use chrono::prelude::*;
+use chrono::NaiveDateTime;
+
+use rocket::http::CookieJar;
+use rocket::serde::json::Json;
+use rocket::State;
+
+fn hash_recovery_codes(recovery_codes: &[String]) -> Result<Vec<String>, GenericError> {
+    recovery_codes
+        .iter()
+        .map(|recovery_code| {
+            argon2::hash_encoded(
+                recovery_code.as_bytes(),
+                USER_AUTH_SALT.as_bytes(),
+                &USER_AUTH_ARGON2_CONFIG,
+            )
+            .map_err(|_| GenericError::ServerInternalError)
+        })
+        .collect()
+}
+
+fn replace_recovery_codes(
+    c: &PgConnection,
+    user_id: i32,
+    codes_hashed: Vec<String>,
+    current_time: NaiveDateTime,
+) -> QueryResult<usize> {
+    diesel::delete(
+        database::totp_recovery_codes::table
+            .filter(database::totp_recovery_codes::user_id.eq(user_id)),
+    )
+    .execute(c)?;
+    let new_recovery_codes: Vec<NewTotpRecoveryCode> = codes_hashed
+        .into_iter()
+        .map(|code_hashed| NewTotpRecoveryCode {
+            user_id,
+            code_hashed,
+            created_time: current_time,
+        })
+        .collect();
+    diesel::insert_into(database::totp_recovery_codes::table)
+        .values(new_recovery_codes)
+        .execute(c)
+}
+
+// The matched step is only accepted if no other request has used it or a later one
+fn consume_totp_step(c: &PgConnection, user_id: i32, step: i64) -> QueryResult<bool> {
+    diesel::update(
+        database::users::table.find(user_id).filter(
+            database::users::totp_last_used_step
+                .is_null()
+                .or(database::users::totp_last_used_step.lt(step)),
+        ),
+    )
+    .set(database::users::totp_last_used_step.eq(Some(step)))
+    .execute(c)
+    .map(|updated_count| updated_count == 1)
+}
+
+async fn verify_user_totp_code(
+    db: &MainDatabaseConnection,
+    user: &User,
+    code: &str,
+) -> Result<(), GenericError> {
+    let secret = user
+        .totp_secret
+        .as_ref()
+        .ok_or(GenericError::TotpNotEnrolledError)?;
+    let step = verify_totp(secret, code, user.totp_last_used_step)
+        .ok_or(GenericError::TotpCodeIncorrectError)?;
+    let user_id = user.id;
+    match db.run(move |c| consume_totp_step(c, user_id, step)).await? {
+        true => Ok(()),
+        false => Err(GenericError::TotpCodeIncorrectError),
+    }
+}
+
+async fn verify_user_recovery_code(
+    db: &MainDatabaseConnection,
+    user_id: i32,
+    recovery_code: &str,
+) -> Result<(), GenericError> {
+    let recovery_code = normalize_recovery_code(recovery_code);
+    let unused_codes: Vec<(i32, String)> = db
+        .run(move |c| {
+            database::totp_recovery_codes::table
+                .select((
+                    database::totp_recovery_codes::id,
+                    database::totp_recovery_codes::code_hashed,
+                ))
+                .filter(database::totp_recovery_codes::user_id.eq(user_id))
+                .filter(database::totp_recovery_codes::used_time.is_null())
+                .get_results(c)
+        })
+        .await?;
+    let (matched_code_id, _) = unused_codes
+        .into_iter()
+        .find(|(_, code_hashed)| {
+            argon2::verify_encoded(code_hashed, recovery_code.as_bytes()).unwrap_or(false)
+        })
+        .ok_or(GenericError::TotpCodeIncorrectError)?;
+    let current_time = Utc::now().naive_utc();
+    match db
+        .run(move |c| {
+            diesel::update(
+                database::totp_recovery_codes::table
+                    .find(matched_code_id)
+                    .filter(database::totp_recovery_codes::used_time.is_null()),
+            )
+            .set(database::totp_recovery_codes::used_time.eq(Some(current_time)))
+            .execute(c)
+        })
+        .await?
+    {
+        1 => Ok(()),
+        _ => Err(GenericError::TotpCodeIncorrectError),
+    }
+}
+
+#[post("/enroll")]
+pub async fn enroll_totp(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+) -> GenericResult<TotpEnrollmentDigest> {
+    let user: User = db
+        .run(move |c| {
+            database::users::table
+                .find(user_digest.user_id)
+                .get_result(c)
+        })
+        .await?;
+    if user.totp_enabled {
+        return Err(GenericError::TotpAlreadyEnabledError);
+    }
+    let secret = gen_totp_secret();
+    let user_id = user.id;
+    let account_name = user
+        .username
+        .or(user.phone_number)
+        .unwrap_or_else(|| format!("user{}", user_id));
+    let otpauth_uri = gen_totp_uri(&secret, &account_name);
+    let qrcode_svg = gen_qrcode_svg(&otpauth_uri)?;
+    let stored_secret = secret.clone();
+    db.run(move |c| {
+        diesel::update(database::users::table.find(user_id))
+            .set((
+                database::users::totp_secret.eq(Some(stored_secret)),
+                database::users::totp_last_used_step.eq(None::<i64>),
+            ))
+            .execute(c)
+    })
+    .await?;
+    SuccessResponse::build(TotpEnrollmentDigest {
+        secret,
+        otpauth_uri,
+        qrcode_svg,
+    })
+}
+
+#[post("/activate", data = "<totp_code_data>")]
+pub async fn activate_totp(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    totp_code_data: Json<ClientTotpCodeData>,
+    cookies: &CookieJar<'_>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
+) -> GenericResult<Vec<String>> {
+    rate_limit
+        .check_identity(rate_limiter, &db, &user_digest.user_id.to_string())
+        .await?;
+    let user: User = db
+        .run(move |c| {
+            database::users::table
+                .find(user_digest.user_id)
+                .get_result(c)
+        })
+        .await?;
+    if user.totp_enabled {
+        return Err(GenericError::TotpAlreadyEnabledError);
+    }
+    verify_user_totp_code(&db, &user, &totp_code_data.code).await?;
+    let recovery_codes = gen_recovery_codes();
+    let codes_hashed = hash_recovery_codes(&recovery_codes)?;
+    let current_time = Utc::now().naive_utc();
+    let user_id = user.id;
+    db.run(move |c| {
+        c.transaction(|| {
+            diesel::update(database::users::table.find(user_id))
+                .set(database::users::totp_enabled.eq(true))
+                .execute(c)?;
+            replace_recovery_codes(c, user_id, codes_hashed, current_time)
+        })
+    })
+    .await?;
+    let token_cookie = gen_totp_verified_token_cookie(user.id, user.user_role)
+        .map_err(|_| GenericError::ServerInternalError)?;
+    cookies.add(token_cookie);
+    SuccessResponse::build(recovery_codes)
+}
+
+#[post("/verify", data = "<totp_verify_data>")]
+pub async fn verify_totp_login(
+    db: MainDatabaseConnection,
+    totp_pending: TotpPendingAuth,
+    totp_verify_data: Json<ClientTotpVerifyData>,
+    cookies: &CookieJar<'_>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
+) -> GenericResult<UserLoggedInDigest> {
+    rate_limit
+        .check_identity(rate_limiter, &db, &totp_pending.user_id.to_string())
+        .await?;
+    let user: User = db
+        .run(move |c| {
+            database::users::table
+                .find(totp_pending.user_id)
+                .get_result(c)
+        })
+        .await?;
+    if is_account_locked(&user) {
+        return Err(GenericError::AccountLockedError);
+    }
+    let verify_result = match (&totp_verify_data.code, &totp_verify_data.recovery_code) {
+        (Some(code), _) => verify_user_totp_code(&db, &user, code).await,
+        (None, Some(recovery_code)) => verify_user_recovery_code(&db, user.id, recovery_code).await,
+        (None, None) => return Err(GenericError::InvalidInputError),
+    };
+    let user_id = user.id;
+    match verify_result {
+        Ok(_) => {
+            db.run(move |c| record_login_success(c, user_id)).await?;
+            let token_cookie = gen_totp_verified_token_cookie(user.id, user.user_role)
+                .map_err(|_| GenericError::ServerInternalError)?;
+            cookies.add(token_cookie);
+            SuccessResponse::build(UserLoggedInDigest {
+                username: user.username,
+                user_role: user.user_role,
+                totp_pending: false,
+            })
+        }
+        Err(GenericError::TotpCodeIncorrectError) => {
+            let client_ip = rate_limit.client_ip.map(|ip| ip.to_string());
+            match db
+                .run(move |c| record_login_failure(c, user_id, client_ip))
+                .await?
+            {
+                true => Err(GenericError::AccountLockedError),
+                false => Err(GenericError::TotpCodeIncorrectError),
+            }
+        }
+        Err(error) => Err(error),
+    }
+}
+
+#[post("/disable", data = "<totp_code_data>")]
+pub async fn disable_totp(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    totp_code_data: Json<ClientTotpCodeData>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
+) -> GenericResult<String> {
+    let user_id = user_digest.user_id;
+    rate_limit
+        .check_identity(rate_limiter, &db, &user_id.to_string())
+        .await?;
+    let user_role = user_digest.user_role;
+    let totp_required = db
+        .run(move |c| {
+            database::totp_policies::table
+                .find(user_role)
+                .select(database::totp_policies::totp_required)
+                .get_result::<bool>(c)
+                .optional()
+        })
+        .await?
+        .unwrap_or(false);
+    if totp_required {
+        return Err(GenericError::TotpRequiredError);
+    }
+    let user: User = db
+        .run(move |c| database::users::table.find(user_id).get_result(c))
+        .await?;
+    if !user.totp_enabled {
+        return Err(GenericError::TotpNotEnrolledError);
+    }
+    verify_user_totp_code(&db, &user, &totp_code_data.code).await?;
+    db.run(move |c| {
+        c.transaction(|| {
+            diesel::delete(
+                database::totp_recovery_codes::table
+                    .filter(database::totp_recovery_codes::user_id.eq(user_id)),
+            )
+            .execute(c)?;
+            diesel::update(database::users::table.find(user_id))
+                .set((
+                    database::users::totp_enabled.eq(false),
+                    database::users::totp_secret.eq(None::<String>),
+                    database::users::totp_last_used_step.eq(None::<i64>),
+                ))
+                .execute(c)
+        })
+    })
+    .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
+#[post("/regenerate_recovery_codes", data = "<totp_code_data>")]
+pub async fn regenerate_recovery_codes(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    totp_code_data: Json<ClientTotpCodeData>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
+) -> GenericResult<Vec<String>> {
+    rate_limit
+        .check_identity(rate_limiter, &db, &user_digest.user_id.to_string())
+        .await?;
+    let user: User = db
+        .run(move |c| {
+            database::users::table
+                .find(user_digest.user_id)
+                .get_result(c)
+        })
+        .await?;
+    if !user.totp_enabled {
+        return Err(GenericError::TotpNotEnrolledError);
+    }
+    verify_user_totp_code(&db, &user, &totp_code_data.code).await?;
+    let recovery_codes = gen_recovery_codes();
+    let codes_hashed = hash_recovery_codes(&recovery_codes)?;
+    let current_time = Utc::now().naive_utc();
+    db.run(move |c| {
+        c.transaction(|| replace_recovery_codes(c, user.id, codes_hashed, current_time))
+    })
+    .await?;
+    SuccessResponse::build(recovery_codes)
+}
+
+#[get("/get_policies")]
+pub async fn get_totp_policies(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+) -> GenericResult<Vec<TotpPolicy>> {
+    SuccessResponse::build(
+        db.run(|c| {
+            database::totp_policies::table
+                .order(database::totp_policies::user_role)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[post("/set_policy", data = "<set_totp_policy_data>")]
+pub async fn set_totp_policy(
+    db: MainDatabaseConnection,
+    admin: AdminAuth,
+    set_totp_policy_data: Json<ClientSetTotpPolicyData>,
+) -> GenericResult<String> {
+    let current_time = Utc::now().naive_utc();
+    db.run(move |c| {
+        diesel::insert_into(database::totp_policies::table)
+            .values((
+                database::totp_policies::user_role.eq(set_totp_policy_data.user_role),
+                database::totp_policies::totp_required.eq(set_totp_policy_data.totp_required),
+                database::totp_policies::updated_by.eq(Some(admin.user_id)),
+                database::totp_policies::updated_time.eq(Some(current_time)),
+            ))
+            .on_conflict(database::totp_policies::user_role)
+            .do_update()
+            .set((
+                database::totp_policies::totp_required.eq(set_totp_policy_data.totp_required),
+                database::totp_policies::updated_by.eq(Some(admin.user_id)),
+                database::totp_policies::updated_time.eq(Some(current_time)),
+            ))
+            .execute(c)
+    })
+    .await?;
+    SuccessResponse::build("完成".to_string())
+}
@@ -1,26 +1,35 @@
 use crate::auth::{
-    gen_token_cookie, AdminAuth, StaffAuth, UserDigest, USER_AUTH_ARGON2_CONFIG, USER_AUTH_SALT,
+    bind_wechat_identity, clear_lockout, gen_token_cookie, gen_totp_pending_token_cookie,
+    is_account_locked, record_login_failure, record_login_success, resolve_or_create_wechat_user,
+    resolve_wechat_user, unbind_wechat_identities, AdminAuth, StaffAuth, UserDigest,
+    WechatIdentityClaim, USER_AUTH_ARGON2_CONFIG, USER_AUTH_SALT,
+};
+use crate::auxiliary::{
+    check_password_policy, fetch_wechat_openid, fetch_wechat_session, fetch_wechat_userinfo,
+    normalize_phone_number, read_config_or, GenericError, GenericResult, RateLimit,
+    RateLimiterState, SmsSenderState, SuccessResponse, WECHAT_APPID, WECHAT_MINIPROGRAM_APPID,
 };
-use crate::auxiliary::{GenericError, GenericResult, SuccessResponse};
-use crate::auxiliary::{WECHAT_APPID, WECHAT_APPSECRET};
 use crate::database::{self, MainDatabaseConnection};
 use crate::models::*;
 
 use diesel::prelude::*;
+use diesel::PgConnection;
 
 use chrono::prelude::*;
-use chrono::NaiveDateTime;
-
-use isahc::{self, AsyncReadResponseExt};
+use chrono::{Duration, NaiveDateTime};
 
 use rocket::http::{Cookie, CookieJar};
 use rocket::serde::json::Json;
+use rocket::State;
+
+use uuid::Uuid;
 
 use lazy_static;
 
 lazy_static! {
-    static ref WECHAT_USERINFO_URL: &'static str =
-        "https://api.weixin.qq.com/sns/userinfo?access_token={}&openid={}&lang=zh_CN";
+    static ref SMS_CODE_VALID_SECONDS: i64 = read_config_or("SMS_CODE_VALID_SECONDS", 300);
+    static ref SMS_CODE_RESEND_SECONDS: i64 = read_config_or("SMS_CODE_RESEND_SECONDS", 60);
+    static ref SMS_CODE_MAX_ATTEMPTS: i32 = read_config_or("SMS_CODE_MAX_ATTEMPTS", 5);
 }
 
 #[get("/verify_login")]
@@ -38,6 +47,22 @@ pub async fn verify_login(
     SuccessResponse::build(UserLoggedInDigest {
         username: query_result.username,
         user_role: query_result.user_role,
+        totp_pending: false,
+    })
+}
+
+// Accounts with TOTP enabled only get a short-lived token for the second step
+fn issue_login_cookie(user: User, cookies: &CookieJar<'_>) -> GenericResult<UserLoggedInDigest> {
+    let token_cookie = match user.totp_enabled {
+        true => gen_totp_pending_token_cookie(user.id, user.user_role),
+        false => gen_token_cookie(user.id, user.user_role),
+    }
+    .map_err(|_| GenericError::ServerInternalError)?;
+    cookies.add(token_cookie);
+    SuccessResponse::build(UserLoggedInDigest {
+        username: user.username,
+        user_role: user.user_role,
+        totp_pending: user.totp_enabled,
     })
 }
 
@@ -46,7 +71,12 @@ pub async fn login(
     db: MainDatabaseConnection,
     login_data: Json<ClientUsernamePasswordData>,
     cookies: &CookieJar<'_>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
 ) -> GenericResult<UserLoggedInDigest> {
+    rate_limit
+        .check_identity(rate_limiter, &db, &login_data.username)
+        .await?;
     let username = login_data.username.to_owned();
     match db
         .run(move |c| {
@@ -56,27 +86,36 @@ pub async fn login(
         })
         .await
     {
-        Ok(query_result) => match query_result.password_hashed {
-            Some(password_hashed) => {
-                match argon2::verify_encoded(&password_hashed, &login_data.password.as_bytes()) {
-                    Ok(verify_result) => match verify_result {
-                        true => match gen_token_cookie(query_result.id, query_result.user_role) {
-                            Ok(token_cookie) => {
-                                cookies.add(token_cookie);
-                                SuccessResponse::build(UserLoggedInDigest {
-                                    user_role: query_result.user_role,
-                                    username: query_result.username,
-                                })
+        Ok(query_result) => {
+            if is_account_locked(&query_result) {
+                return Err(GenericError::AccountLockedError);
+            }
+            let user_id = query_result.id;
+            match &query_result.password_hashed {
+                Some(password_hashed) => {
+                    match argon2::verify_encoded(password_hashed, login_data.password.as_bytes()) {
+                        Ok(verify_result) => match verify_result {
+                            true => {
+                                db.run(move |c| record_login_success(c, user_id)).await?;
+                                issue_login_cookie(query_result, cookies)
+                            }
+                            false => {
+                                let client_ip = rate_limit.client_ip.map(|ip| ip.to_string());
+                                match db
+                                    .run(move |c| record_login_failure(c, user_id, client_ip))
+                                    .await?
+                                {
+                                    true => Err(GenericError::AccountLockedError),
+                                    false => Err(GenericError::PasswordIncorrectError),
+                                }
                             }
-                            Err(_) => Err(GenericError::ServerInternalError),
                         },
-                        false => Err(GenericError::PasswordIncorrectError),
-                    },
-                    Err(_) => Err(GenericError::AuthError),
+                        Err(_) => Err(GenericError::AuthError),
+                    }
                 }
+                None => Err(GenericError::PasswordNotSetError),
             }
-            None => Err(GenericError::PasswordNotSetError),
-        },
+        }
         Err(_) => Err(GenericError::UserNotExistError),
     }
 }
@@ -86,7 +125,12 @@ pub async fn register(
     db: MainDatabaseConnection,
     register_data: Json<ClientUsernamePasswordData>,
     cookies: &CookieJar<'_>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
 ) -> GenericResult<UserLoggedInDigest> {
+    rate_limit
+        .check_identity(rate_limiter, &db, &register_data.username)
+        .await?;
     let username = register_data.username.to_owned();
     match db
         .run(move |c| {
@@ -98,6 +142,7 @@ pub async fn register(
     {
         Ok(_) => Err(GenericError::UserAlreadyExistsError),
         Err(_) => {
+            check_password_policy(&register_data.password)?;
             let password_hashed = argon2::hash_encoded(
                 &register_data.password.as_bytes(),
                 USER_AUTH_SALT.as_bytes(),
@@ -129,6 +174,7 @@ pub async fn register(
                             SuccessResponse::build(UserLoggedInDigest {
                                 user_role: inserted_user.user_role,
                                 username: inserted_user.username,
+                                totp_pending: false,
                             })
                         }
                         Err(_) => Err(GenericError::ServerInternalError),
@@ -196,6 +242,30 @@ pub async fn change_user_role(
     }
 }
 
+#[post("/set_report_reviewer", data = "<set_report_reviewer_data>")]
+pub async fn set_report_reviewer(
+    db: MainDatabaseConnection,
+    set_report_reviewer_data: Json<ClientSetReportReviewerData>,
+    _admin: AdminAuth,
+) -> GenericResult<String> {
+    let target_user_id = set_report_reviewer_data.user_id;
+    let report_reviewer = set_report_reviewer_data.report_reviewer;
+    let user: User = db
+        .run(move |c| database::users::table.find(target_user_id).get_result(c))
+        .await?;
+    // Only staff and admins can review, regular users can't be granted the permission
+    if report_reviewer && user.user_role == RoleEnum::User {
+        return Err(GenericError::InvalidInputError);
+    }
+    db.run(move |c| {
+        diesel::update(database::users::table.find(target_user_id))
+            .set(database::users::report_reviewer.eq(report_reviewer))
+            .execute(c)
+    })
+    .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
 #[post("/remove_user", data = "<remove_user_data>")]
 pub async fn remove_user(
     db: MainDatabaseConnection,
@@ -216,37 +286,44 @@ pub async fn remove_user(
 #[post("/change_password", data = "<change_password_data>")]
 pub async fn change_password(
     db: MainDatabaseConnection,
-    change_password_data: Json<ClientUsernamePasswordData>,
+    change_password_data: Json<ClientChangePasswordData>,
     user_digest: UserDigest,
 ) -> GenericResult<String> {
     let target_user_id = user_digest.user_id;
     let query_result: User = db
         .run(move |c| database::users::table.find(target_user_id).get_result(c))
         .await?;
-    if query_result
-        .username
-        .ok_or(GenericError::PermissionDeniedError)?
-        == change_password_data.username
+    let current_password_hashed = query_result
+        .password_hashed
+        .ok_or(GenericError::PasswordNotSetError)?;
+    match argon2::verify_encoded(
+        &current_password_hashed,
+        change_password_data.old_password.as_bytes(),
+    ) {
+        Ok(true) => {}
+        Ok(false) => return Err(GenericError::PasswordIncorrectError),
+        Err(_) => return Err(GenericError::AuthError),
+    }
+    if change_password_data.new_password == change_password_data.old_password {
+        return Err(GenericError::PasswordUnchangedError);
+    }
+    check_password_policy(&change_password_data.new_password)?;
+    let password_hashed = argon2::hash_encoded(
+        change_password_data.new_password.as_bytes(),
+        USER_AUTH_SALT.as_bytes(),
+        &USER_AUTH_ARGON2_CONFIG,
+    )
+    .map_err(|_| GenericError::ServerInternalError)?;
+    match db
+        .run(move |c| {
+            diesel::update(database::users::table.find(target_user_id))
+                .set(database::users::password_hashed.eq(password_hashed))
+                .execute(c)
+        })
+        .await?
     {
-        let password_hashed = argon2::hash_encoded(
-            &change_password_data.password.as_bytes(),
-            USER_AUTH_SALT.as_bytes(),
-            &USER_AUTH_ARGON2_CONFIG,
-        )
-        .map_err(|_| GenericError::ServerInternalError)?;
-        match db
-            .run(move |c| {
-                diesel::update(database::users::table.find(target_user_id))
-                    .set(database::users::password_hashed.eq(password_hashed))
-                    .execute(c)
-            })
-            .await?
-        {
-            1 => SuccessResponse::build("完成".to_string()),
-            _ => Err(GenericError::ServerInternalError),
-        }
-    } else {
-        Err(GenericError::PermissionDeniedError)
+        1 => SuccessResponse::build("完成".to_string()),
+        _ => Err(GenericError::ServerInternalError),
     }
 }
 
@@ -263,88 +340,208 @@ pub async fn wechat_login(
     db: MainDatabaseConnection,
     wechat_login_data: Json<ClientWechatLoginData>,
     cookies: &CookieJar<'_>,
+    _rate_limit: RateLimit,
 ) -> GenericResult<UserLoggedInDigest> {
-    let parsed_open_id_reponse: WechatOpenIdResponse = isahc::get_async(format!(
-        "https://api.weixin.qq.com/sns/oauth2/access_token?\
-    appid={}&secret={}&code={}&grant_type=authorization_code\
-    ",
-        *WECHAT_APPID, *WECHAT_APPSECRET, &wechat_login_data.code
-    ))
-    .await
-    .map_err(|error| {
-        error!("获取OpenId时出错：{:?}", error);
-        GenericError::GetWechatOpenIdError
-    })?
-    .json()
-    .await
-    .map_err(|error| {
-        error!("获取OpenId时出错：{:?}", error);
-        GenericError::GetWechatOpenIdError
-    })?;
-    let openid = parsed_open_id_reponse.openid.to_owned();
-    match db
+    let parsed_open_id_reponse = fetch_wechat_openid(&wechat_login_data.code).await?;
+    let claim = WechatIdentityClaim {
+        appid: WECHAT_APPID.to_string(),
+        openid: parsed_open_id_reponse.openid.to_owned(),
+        unionid: parsed_open_id_reponse.unionid.to_owned(),
+    };
+    let (claim, parsed_user) = db
+        .run(move |c| resolve_wechat_user(c, &claim).map(|user| (claim, user)))
+        .await?;
+    match parsed_user {
+        Some(parsed_user) => issue_login_cookie(parsed_user, cookies),
+        None => {
+            let parsed_userinfo_reponse = fetch_wechat_userinfo(
+                &parsed_open_id_reponse.access_token,
+                &parsed_open_id_reponse.openid,
+            )
+            .await?;
+            // The userinfo may reveal a unionid already known from another WeChat app
+            let claim = WechatIdentityClaim {
+                unionid: claim.unionid.or(parsed_userinfo_reponse.unionid),
+                ..claim
+            };
+            let username = Some(parsed_userinfo_reponse.nickname);
+            let parsed_user = db
+                .run(move |c| resolve_or_create_wechat_user(c, &claim, username))
+                .await?;
+            issue_login_cookie(parsed_user, cookies)
+        }
+    }
+}
+
+#[post("/wechat_miniprogram_login", data = "<wechat_login_data>")]
+pub async fn wechat_miniprogram_login(
+    db: MainDatabaseConnection,
+    wechat_login_data: Json<ClientWechatLoginData>,
+    cookies: &CookieJar<'_>,
+    _rate_limit: RateLimit,
+) -> GenericResult<UserLoggedInDigest> {
+    let parsed_session_response = fetch_wechat_session(&wechat_login_data.code).await?;
+    let claim = WechatIdentityClaim {
+        appid: WECHAT_MINIPROGRAM_APPID
+            .clone()
+            .ok_or(GenericError::GetWechatSessionError)?,
+        openid: parsed_session_response.openid,
+        unionid: parsed_session_response.unionid,
+    };
+    let parsed_user = db
+        .run(move |c| resolve_or_create_wechat_user(c, &claim, None))
+        .await?;
+    issue_login_cookie(parsed_user, cookies)
+}
+
+#[post("/request_sms_code", data = "<sms_code_request_data>")]
+pub async fn request_sms_code(
+    db: MainDatabaseConnection,
+    sms_code_request_data: Json<ClientRequestSmsCodeData>,
+    sms_sender: &State<SmsSenderState>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
+) -> GenericResult<String> {
+    let phone_number = normalize_phone_number(&sms_code_request_data.phone_number)?;
+    rate_limit
+        .check_identity(rate_limiter, &db, &phone_number)
+        .await?;
+    let query_phone_number = phone_number.clone();
+    let current_time = Utc::now().naive_utc();
+    let resend_time = current_time - Duration::seconds(*SMS_CODE_RESEND_SECONDS);
+    let recent_count: i64 = db
         .run(move |c| {
-            database::users::table
-                .filter(database::users::wechat_id.eq_all(Some(openid)))
-                .get_result::<User>(c)
+            database::sms_codes::table
+                .filter(database::sms_codes::phone_number.eq(query_phone_number))
+                .filter(database::sms_codes::created_time.gt(resend_time))
+                .count()
+                .get_result(c)
+        })
+        .await?;
+    if recent_count > 0 {
+        return Err(GenericError::SmsCodeTooFrequentError);
+    }
+    let code = format!("{:06}", Uuid::new_v4().as_u128() % 1_000_000);
+    let code_hashed = argon2::hash_encoded(
+        code.as_bytes(),
+        USER_AUTH_SALT.as_bytes(),
+        &USER_AUTH_ARGON2_CONFIG,
+    )
+    .map_err(|_| GenericError::ServerInternalError)?;
+    let new_sms_code = NewSmsCode {
+        phone_number: phone_number.clone(),
+        code_hashed,
+        created_time: current_time,
+        expiration_time: current_time + Duration::seconds(*SMS_CODE_VALID_SECONDS),
+    };
+    db.run(move |c| {
+        diesel::insert_into(database::sms_codes::table)
+            .values(new_sms_code)
+            .execute(c)
+    })
+    .await?;
+    sms_sender
+        .sender
+        .send(
+            &phone_number,
+            &format!(
+                "您的验证码为{}，{}分钟内有效。",
+                code,
+                *SMS_CODE_VALID_SECONDS / 60
+            ),
+        )
+        .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
+#[post("/sms_login", data = "<sms_login_data>")]
+pub async fn sms_login(
+    db: MainDatabaseConnection,
+    sms_login_data: Json<ClientSmsLoginData>,
+    cookies: &CookieJar<'_>,
+    rate_limit: RateLimit,
+    rate_limiter: &State<RateLimiterState>,
+) -> GenericResult<UserLoggedInDigest> {
+    let phone_number = normalize_phone_number(&sms_login_data.phone_number)?;
+    rate_limit
+        .check_identity(rate_limiter, &db, &phone_number)
+        .await?;
+    let query_phone_number = phone_number.clone();
+    let current_time = Utc::now().naive_utc();
+    let sms_code: SmsCode = db
+        .run(move |c| {
+            database::sms_codes::table
+                .filter(database::sms_codes::phone_number.eq(query_phone_number))
+                .filter(database::sms_codes::consumed_time.is_null())
+                .filter(database::sms_codes::expiration_time.gt(current_time))
+                .order(database::sms_codes::id.desc())
+                .first(c)
                 .optional()
         })
         .await?
-    {
-        Some(parsed_user) => match gen_token_cookie(parsed_user.id, parsed_user.user_role) {
-            Ok(token_cookie) => {
-                cookies.add(token_cookie);
-                SuccessResponse::build(UserLoggedInDigest {
-                    user_role: parsed_user.user_role,
-                    username: parsed_user.username,
+        .ok_or(GenericError::SmsCodeExpiredError)?;
+    if sms_code.attempts >= *SMS_CODE_MAX_ATTEMPTS {
+        return Err(GenericError::SmsCodeExpiredError);
+    }
+    let sms_code_id = sms_code.id;
+    match argon2::verify_encoded(&sms_code.code_hashed, sms_login_data.code.trim().as_bytes()) {
+        Ok(true) => {
+            // Only one request may consume a code
+            let consumed_count = db
+                .run(move |c| {
+                    diesel::update(
+                        database::sms_codes::table
+                            .find(sms_code_id)
+                            .filter(database::sms_codes::consumed_time.is_null()),
+                    )
+                    .set(database::sms_codes::consumed_time.eq(Some(current_time)))
+                    .execute(c)
                 })
+                .await?;
+            if consumed_count != 1 {
+                return Err(GenericError::SmsCodeExpiredError);
             }
-            Err(_) => Err(GenericError::ServerInternalError),
-        },
+        }
+        Ok(false) => {
+            db.run(move |c| {
+                diesel::update(database::sms_codes::table.find(sms_code_id))
+                    .set(database::sms_codes::attempts.eq(database::sms_codes::attempts + 1))
+                    .execute(c)
+            })
+            .await?;
+            return Err(GenericError::SmsCodeIncorrectError);
+        }
+        Err(_) => return Err(GenericError::AuthError),
+    }
+    let query_phone_number = phone_number.clone();
+    let existing_user: Option<User> = db
+        .run(move |c| {
+            database::users::table
+                .filter(database::users::phone_number.eq(Some(query_phone_number)))
+                .get_result(c)
+                .optional()
+        })
+        .await?;
+    let user = match existing_user {
+        Some(user) => user,
         None => {
-            let parsed_userinfo_reponse: WechatUserinfoResponse = isahc::get_async(format!(
-                "https://api.weixin.qq.com/sns/userinfo?access_token={}&openid={}&lang=zh_CN",
-                parsed_open_id_reponse.access_token, parsed_open_id_reponse.openid
-            ))
-            .await
-            .map_err(|error| {
-                error!("获取Userinfo时出错：{:?}", error);
-                GenericError::GetWechatUserinfoError
-            })?
-            .json()
-            .await
-            .map_err(|error| {
-                error!("获取Userinfo时出错：{:?}", error);
-                GenericError::GetWechatUserinfoError
-            })?;
-            let current_time = Utc::now().naive_utc();
             let new_user = NewUserData {
+                username: None,
+                wechat_id: None,
+                user_role: RoleEnum::User,
                 password_hashed: None,
-                phone_number: None,
+                phone_number: Some(phone_number),
                 sign_up_time: current_time,
-                user_role: RoleEnum::User,
-                username: Some(parsed_userinfo_reponse.nickname),
-                wechat_id: Some(parsed_open_id_reponse.openid),
             };
-            let insert_result: User = db
-                .run(move |c| {
-                    diesel::insert_into(database::users::table)
-                        .values(new_user)
-                        .get_result(c)
-                })
-                .await?;
-            match gen_token_cookie(insert_result.id, insert_result.user_role) {
-                Ok(token_cookie) => {
-                    cookies.add(token_cookie);
-                    SuccessResponse::build(UserLoggedInDigest {
-                        username: insert_result.username,
-                        user_role: insert_result.user_role,
-                    })
-                }
-                Err(_) => Err(GenericError::ServerInternalError),
-            }
+            db.run(move |c| {
+                diesel::insert_into(database::users::table)
+                    .values(new_user)
+                    .get_result(c)
+            })
+            .await?
         }
-    }
+    };
+    issue_login_cookie(user, cookies)
 }
 
 #[get("/get_statistics")]
@@ -386,3 +583,287 @@ pub async fn get_user_statistics(
         user,
     })
 }
+
+#[get("/get_locked_users/<page>")]
+pub async fn get_locked_users(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    page: i32,
+) -> GenericResult<Vec<LockedUserDigest>> {
+    let current_time = Utc::now().naive_utc();
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::users::table
+                .select((
+                    database::users::id,
+                    database::users::username,
+                    database::users::lockout_count,
+                    database::users::locked_until,
+                ))
+                .filter(database::users::locked_until.gt(current_time))
+                .order(database::users::locked_until.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_lockout_events/<page>")]
+pub async fn get_lockout_events(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    page: i32,
+) -> GenericResult<Vec<LockoutEvent>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::lockout_events::table
+                .order(database::lockout_events::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[post("/clear_lock", data = "<clear_lock_data>")]
+pub async fn clear_lock(
+    db: MainDatabaseConnection,
+    admin: AdminAuth,
+    clear_lock_data: Json<ClientClearLockData>,
+) -> GenericResult<String> {
+    let target_user_id = clear_lock_data.user_id;
+    match db
+        .run(move |c| clear_lockout(c, target_user_id, admin.user_id))
+        .await?
+    {
+        1 => SuccessResponse::build("完成".to_string()),
+        _ => Err(GenericError::UserNotExistError),
+    }
+}
+
+#[post("/bind_wechat", data = "<wechat_login_data>")]
+pub async fn bind_wechat(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+    wechat_login_data: Json<ClientWechatLoginData>,
+) -> GenericResult<String> {
+    let parsed_open_id_reponse = fetch_wechat_openid(&wechat_login_data.code).await?;
+    let claim = WechatIdentityClaim {
+        appid: WECHAT_APPID.to_string(),
+        openid: parsed_open_id_reponse.openid,
+        unionid: parsed_open_id_reponse.unionid,
+    };
+    let user_id = user_digest.user_id;
+    db.run(move |c| bind_wechat_identity(c, user_id, &claim))
+        .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
+#[post("/unbind_wechat")]
+pub async fn unbind_wechat(
+    db: MainDatabaseConnection,
+    user_digest: UserDigest,
+) -> GenericResult<String> {
+    let user_id = user_digest.user_id;
+    let (user, identity_count): (User, i64) = db
+        .run(move |c| {
+            let user = database::users::table.find(user_id).get_result(c)?;
+            let identity_count = database::wechat_identities::table
+                .filter(database::wechat_identities::user_id.eq(user_id))
+                .count()
+                .get_result(c)?;
+            Ok::<_, diesel::result::Error>((user, identity_count))
+        })
+        .await?;
+    if user.wechat_id.is_none() && identity_count == 0 {
+        return Err(GenericError::WechatNotBoundError);
+    }
+    if user.password_hashed.is_none() && user.phone_number.is_none() {
+        return Err(GenericError::LastLoginMethodError);
+    }
+    db.run(move |c| unbind_wechat_identities(c, user_id))
+        .await?;
+    SuccessResponse::build("完成".to_string())
+}
+
+// Keeps the target's value, taking the source's only when the target has none
+fn merge_login_field(
+    target_value: Option<String>,
+    source_value: Option<String>,
+) -> Result<Option<String>, GenericError> {
+    match (target_value, source_value) {
+        (Some(target_value), Some(source_value)) if target_value != source_value => {
+            Err(GenericError::MergeConflictError)
+        }
+        (Some(target_value), _) => Ok(Some(target_value)),
+        (None, source_value) => Ok(source_value),
+    }
+}
+
+fn merge_user_accounts(
+    c: &PgConnection,
+    source_user_id: i32,
+    target_user_id: i32,
+) -> Result<User, GenericError> {
+    c.transaction(|| {
+        let source_user: User = database::users::table.find(source_user_id).get_result(c)?;
+        let target_user: User = database::users::table.find(target_user_id).get_result(c)?;
+        let phone_number = merge_login_field(target_user.phone_number, source_user.phone_number)?;
+        let username = target_user.username.or(source_user.username);
+        let password_hashed = target_user.password_hashed.or(source_user.password_hashed);
+        let user_role = target_user.user_role.max(source_user.user_role);
+
+        diesel::update(
+            database::profiles::table.filter(database::profiles::user_id.eq(source_user_id)),
+        )
+        .set(database::profiles::user_id.eq(target_user_id))
+        .execute(c)?;
+        diesel::update(
+            database::reports::table.filter(database::reports::uploader_id.eq(source_user_id)),
+        )
+        .set(database::reports::uploader_id.eq(target_user_id))
+        .execute(c)?;
+        diesel::update(
+            database::reports::table
+                .filter(database::reports::retracted_by.eq(Some(source_user_id))),
+        )
+        .set(database::reports::retracted_by.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::update(
+            database::reports::table
+                .filter(database::reports::reviewed_by.eq(Some(source_user_id))),
+        )
+        .set(database::reports::reviewed_by.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::update(
+            database::notifications::table
+                .filter(database::notifications::user_id.eq(source_user_id)),
+        )
+        .set(database::notifications::user_id.eq(target_user_id))
+        .execute(c)?;
+        // The target's own notification preferences win over the source's
+        let target_preference_count: i64 = database::notification_preferences::table
+            .find(target_user_id)
+            .count()
+            .get_result(c)?;
+        if target_preference_count > 0 {
+            diesel::delete(database::notification_preferences::table.find(source_user_id))
+                .execute(c)?;
+        } else {
+            diesel::update(database::notification_preferences::table.find(source_user_id))
+                .set(database::notification_preferences::user_id.eq(target_user_id))
+                .execute(c)?;
+        }
+        diesel::update(
+            database::report_downloads::table
+                .filter(database::report_downloads::user_id.eq(Some(source_user_id))),
+        )
+        .set(database::report_downloads::user_id.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::update(
+            database::report_downloads::table
+                .filter(database::report_downloads::shared_by.eq(Some(source_user_id))),
+        )
+        .set(database::report_downloads::shared_by.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::update(
+            database::webhook_subscriptions::table
+                .filter(database::webhook_subscriptions::created_by.eq(source_user_id)),
+        )
+        .set(database::webhook_subscriptions::created_by.eq(target_user_id))
+        .execute(c)?;
+        diesel::update(
+            database::lockout_events::table
+                .filter(database::lockout_events::user_id.eq(source_user_id)),
+        )
+        .set(database::lockout_events::user_id.eq(target_user_id))
+        .execute(c)?;
+        diesel::update(
+            database::lockout_events::table
+                .filter(database::lockout_events::cleared_by.eq(Some(source_user_id))),
+        )
+        .set(database::lockout_events::cleared_by.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::update(
+            database::totp_policies::table
+                .filter(database::totp_policies::updated_by.eq(Some(source_user_id))),
+        )
+        .set(database::totp_policies::updated_by.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::update(
+            database::report_templates::table
+                .filter(database::report_templates::updated_by.eq(Some(source_user_id))),
+        )
+        .set(database::report_templates::updated_by.eq(Some(target_user_id)))
+        .execute(c)?;
+        diesel::delete(
+            database::totp_recovery_codes::table
+                .filter(database::totp_recovery_codes::user_id.eq(source_user_id)),
+        )
+        .execute(c)?;
+        // Each WeChat app may only be bound once per account, the surviving account keeps its own
+        // binding and the source's binding of the same app is dropped
+        let mut target_appids: Vec<String> = database::wechat_identities::table
+            .filter(database::wechat_identities::user_id.eq(target_user_id))
+            .select(database::wechat_identities::appid)
+            .load(c)?;
+        if target_user.wechat_id.is_some() {
+            target_appids.push(WECHAT_APPID.to_string());
+        }
+        let wechat_id = match target_appids.contains(&*WECHAT_APPID) {
+            true => target_user.wechat_id,
+            false => source_user.wechat_id,
+        };
+        let dropped_count = diesel::delete(
+            database::wechat_identities::table
+                .filter(database::wechat_identities::user_id.eq(source_user_id))
+                .filter(database::wechat_identities::appid.eq_any(&target_appids)),
+        )
+        .execute(c)?;
+        if dropped_count > 0 {
+            info!(
+                "合并用户{}时舍弃了其{}个与用户{}冲突的微信绑定",
+                source_user_id, dropped_count, target_user_id
+            );
+        }
+        diesel::update(
+            database::wechat_identities::table
+                .filter(database::wechat_identities::user_id.eq(source_user_id)),
+        )
+        .set(database::wechat_identities::user_id.eq(target_user_id))
+        .execute(c)?;
+        // The unique login fields have to leave the source before the target can take them
+        diesel::delete(database::users::table.find(source_user_id)).execute(c)?;
+        diesel::update(database::users::table.find(target_user_id))
+            .set((
+                database::users::wechat_id.eq(wechat_id),
+                database::users::phone_number.eq(phone_number),
+                database::users::username.eq(username),
+                database::users::password_hashed.eq(password_hashed),
+                database::users::user_role.eq(user_role),
+            ))
+            .get_result(c)
+            .map_err(GenericError::from)
+    })
+}
+
+#[post("/merge_users", data = "<merge_users_data>")]
+pub async fn merge_users(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    merge_users_data: Json<ClientMergeUsersData>,
+) -> GenericResult<User> {
+    let source_user_id = merge_users_data.source_user_id;
+    let target_user_id = merge_users_data.target_user_id;
+    if source_user_id == target_user_id {
+        return Err(GenericError::InvalidInputError);
+    }
+    let merged_user = db
+        .run(move |c| merge_user_accounts(c, source_user_id, target_user_id))
+        .await?;
+    info!("用户{}已合并至用户{}", source_user_id, target_user_id);
+    SuccessResponse::build(merged_user)
+}
@@ -0,0 +1,217 @@
+use crate::auth::AdminAuth;
+use crate::auxiliary::{GenericError, GenericResult, SuccessResponse};
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::*;
+use crate::notifications::{
+    gen_webhook_secret, is_valid_webhook_url, redeliver_webhook, PRODUCT_EVENT_TYPES,
+};
+
+use diesel::prelude::*;
+
+use chrono::prelude::*;
+
+use rocket::serde::json::Json;
+
+fn check_subscription_data(
+    url: &str,
+    event_types: &[String],
+    barcode_prefix: &Option<String>,
+) -> Result<(), GenericError> {
+    if !is_valid_webhook_url(url) {
+        return Err(GenericError::InvalidWebhookUrlError);
+    }
+    if event_types.is_empty()
+        || !event_types
+            .iter()
+            .all(|event_type| PRODUCT_EVENT_TYPES.contains(&event_type.as_str()))
+    {
+        return Err(GenericError::InvalidInputError);
+    }
+    match barcode_prefix {
+        Some(barcode_prefix) if !barcode_prefix.chars().all(|c| c.is_ascii_digit()) => {
+            Err(GenericError::InvalidInputError)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[post("/create_subscription", data = "<subscription_data>")]
+pub async fn create_webhook_subscription(
+    db: MainDatabaseConnection,
+    admin: AdminAuth,
+    subscription_data: Json<ClientWebhookSubscriptionData>,
+) -> GenericResult<CreatedWebhookSubscription> {
+    let subscription_data = subscription_data.into_inner();
+    let barcode_prefix = subscription_data
+        .barcode_prefix
+        .filter(|barcode_prefix| !barcode_prefix.is_empty());
+    check_subscription_data(
+        &subscription_data.url,
+        &subscription_data.event_types,
+        &barcode_prefix,
+    )?;
+    let current_time = Utc::now().naive_utc();
+    let secret = gen_webhook_secret();
+    let new_subscription = NewWebhookSubscription {
+        partner_name: subscription_data.partner_name,
+        url: subscription_data.url,
+        secret: secret.to_owned(),
+        event_types: subscription_data.event_types,
+        barcode_prefix,
+        enabled: subscription_data.enabled,
+        created_by: admin.user_id,
+        created_time: current_time,
+        updated_time: current_time,
+    };
+    let subscription: WebhookSubscription = db
+        .run(move |c| {
+            diesel::insert_into(database::webhook_subscriptions::table)
+                .values(new_subscription)
+                .get_result(c)
+        })
+        .await?;
+    SuccessResponse::build(CreatedWebhookSubscription {
+        subscription,
+        secret,
+    })
+}
+
+#[post("/update_subscription", data = "<subscription_data>")]
+pub async fn update_webhook_subscription(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    subscription_data: Json<ClientUpdateWebhookSubscriptionData>,
+) -> GenericResult<WebhookSubscription> {
+    let subscription_data = subscription_data.into_inner();
+    let barcode_prefix = subscription_data
+        .barcode_prefix
+        .filter(|barcode_prefix| !barcode_prefix.is_empty());
+    check_subscription_data(
+        &subscription_data.url,
+        &subscription_data.event_types,
+        &barcode_prefix,
+    )?;
+    let subscription_id = subscription_data.subscription_id;
+    let update_set = UpdateWebhookSubscription {
+        partner_name: subscription_data.partner_name,
+        url: subscription_data.url,
+        event_types: subscription_data.event_types,
+        barcode_prefix,
+        enabled: subscription_data.enabled,
+        updated_time: Utc::now().naive_utc(),
+    };
+    SuccessResponse::build(
+        db.run(move |c| {
+            diesel::update(database::webhook_subscriptions::table.find(subscription_id))
+                .set(update_set)
+                .get_result(c)
+        })
+        .await?,
+    )
+}
+
+#[post("/rotate_secret", data = "<subscription_id_data>")]
+pub async fn rotate_webhook_secret(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    subscription_id_data: Json<ClientWebhookSubscriptionIdData>,
+) -> GenericResult<CreatedWebhookSubscription> {
+    let subscription_id = subscription_id_data.subscription_id;
+    let secret = gen_webhook_secret();
+    let new_secret = secret.to_owned();
+    let subscription: WebhookSubscription = db
+        .run(move |c| {
+            diesel::update(database::webhook_subscriptions::table.find(subscription_id))
+                .set((
+                    database::webhook_subscriptions::secret.eq(new_secret),
+                    database::webhook_subscriptions::updated_time.eq(Utc::now().naive_utc()),
+                ))
+                .get_result(c)
+        })
+        .await?;
+    SuccessResponse::build(CreatedWebhookSubscription {
+        subscription,
+        secret,
+    })
+}
+
+#[post("/remove_subscription", data = "<subscription_id_data>")]
+pub async fn remove_webhook_subscription(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    subscription_id_data: Json<ClientWebhookSubscriptionIdData>,
+) -> GenericResult<String> {
+    let subscription_id = subscription_id_data.subscription_id;
+    match db
+        .run(move |c| {
+            diesel::delete(database::webhook_subscriptions::table.find(subscription_id)).execute(c)
+        })
+        .await?
+    {
+        1 => SuccessResponse::build("完成".to_string()),
+        _ => Err(GenericError::InvalidInputError),
+    }
+}
+
+#[get("/get_subscriptions")]
+pub async fn get_webhook_subscriptions(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+) -> GenericResult<Vec<WebhookSubscription>> {
+    SuccessResponse::build(
+        db.run(|c| {
+            database::webhook_subscriptions::table
+                .order(database::webhook_subscriptions::id)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_deliveries/<page>/<subscription_id>")]
+pub async fn get_filtered_webhook_deliveries(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    page: i32,
+    subscription_id: i32,
+) -> GenericResult<Vec<WebhookDelivery>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::webhook_deliveries::table
+                .filter(database::webhook_deliveries::subscription_id.eq(subscription_id))
+                .order(database::webhook_deliveries::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[get("/get_deliveries/<page>")]
+pub async fn get_webhook_deliveries(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    page: i32,
+) -> GenericResult<Vec<WebhookDelivery>> {
+    SuccessResponse::build(
+        db.run(move |c| {
+            database::webhook_deliveries::table
+                .order(database::webhook_deliveries::id.desc())
+                .limit(10)
+                .offset((page * 10) as i64)
+                .get_results(c)
+        })
+        .await?,
+    )
+}
+
+#[post("/redeliver", data = "<redeliver_data>")]
+pub async fn redeliver_webhook_delivery(
+    db: MainDatabaseConnection,
+    _admin: AdminAuth,
+    redeliver_data: Json<ClientRedeliverWebhookData>,
+) -> GenericResult<WebhookDelivery> {
+    let delivery_id = redeliver_data.delivery_id;
+    SuccessResponse::build(db.run(move |c| redeliver_webhook(c, delivery_id)).await?)
+}
@@ -1 +1,117 @@
+use crate::auth::WechatSignature;
+use crate::auxiliary::{read_config_or, GenericError, QRCODE_DOMAIN_ROOT};
+use crate::database::{self, MainDatabaseConnection};
+use crate::models::*;
 
+use diesel::prelude::*;
+
+use chrono::prelude::*;
+
+use rocket::response::content;
+
+lazy_static! {
+    static ref WECHAT_WELCOME_MESSAGE: String = read_config_or(
+        "WECHAT_WELCOME_MESSAGE",
+        "感谢关注！扫描试剂盒上的二维码或发送试剂盒条码即可查询检测进度。".to_string()
+    );
+}
+
+// WeChat treats this body as "handled, nothing to reply"
+const WECHAT_EMPTY_REPLY: &str = "success";
+
+#[get("/callback?<echostr>")]
+pub async fn validate_wechat_server(_signature: WechatSignature, echostr: String) -> String {
+    echostr
+}
+
+// Barcodes arrive as a parametric QR scene ("qrscene_" prefixed on subscribe),
+// as the scanned kit QR code URL, or typed in as a text message
+fn extract_product_barcode(text: &str) -> Option<String> {
+    let text = text.trim();
+    let text = text.strip_prefix("qrscene_").unwrap_or(text);
+    let text = text
+        .strip_prefix(QRCODE_DOMAIN_ROOT.as_str())
+        .unwrap_or(text);
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+        Some(text.to_string())
+    } else {
+        None
+    }
+}
+
+fn describe_product(product: Option<Product>, product_barcode: &str) -> String {
+    let product = match product {
+        Some(product) => product,
+        None => return format!("未找到条码为{}的试剂盒，请核对后重试。", product_barcode),
+    };
+    if product.profile_id.is_none() {
+        return format!(
+            "试剂盒{}尚未绑定受检者信息，<a href=\"{}{}\">点击此处绑定</a>。",
+            product_barcode, *QRCODE_DOMAIN_ROOT, product_barcode
+        );
+    }
+    let stage_description = match product.current_stage {
+        StageEnum::Initialized => "尚未使用",
+        StageEnum::Submitted => "已提交信息，等待采样",
+        StageEnum::Sampled => "样本已采集，正在检测",
+        StageEnum::Finished => "检测已完成，请登录查看报告",
+    };
+    format!("试剂盒{}：{}。", product_barcode, stage_description)
+}
+
+#[post("/callback", data = "<message>")]
+pub async fn handle_wechat_message(
+    db: MainDatabaseConnection,
+    _signature: WechatSignature,
+    message: String,
+) -> Result<content::Xml<String>, GenericError> {
+    let parsed_message: WechatIncomingMessage =
+        quick_xml::de::from_str(&message).map_err(|error| {
+            warn!("微信消息解析失败：{:?}", error);
+            GenericError::WechatMessageParseError
+        })?;
+    let scanned_text = match (
+        parsed_message.msg_type.as_ref(),
+        parsed_message.event.as_deref(),
+    ) {
+        ("event", Some("subscribe")) | ("event", Some("SCAN")) => {
+            parsed_message.event_key.to_owned()
+        }
+        ("event", Some("scancode_waitmsg")) => parsed_message
+            .scan_code_info
+            .as_ref()
+            .map(|scan_code_info| scan_code_info.scan_result.to_owned()),
+        ("text", _) => parsed_message.content.to_owned(),
+        _ => None,
+    };
+    let reply_content = match scanned_text.as_deref().and_then(extract_product_barcode) {
+        Some(product_barcode) => {
+            let query_barcode = product_barcode.clone();
+            let product: Option<Product> = db
+                .run(move |c| {
+                    database::products::table
+                        .filter(database::products::product_barcode.eq(query_barcode))
+                        .first(c)
+                        .optional()
+                })
+                .await?;
+            describe_product(product, &product_barcode)
+        }
+        None => match (
+            parsed_message.msg_type.as_ref(),
+            parsed_message.event.as_deref(),
+        ) {
+            ("event", Some("subscribe")) => WECHAT_WELCOME_MESSAGE.to_string(),
+            ("text", _) => "请发送试剂盒条码，或扫描试剂盒上的二维码查询检测进度。".to_string(),
+            _ => return Ok(content::Xml(WECHAT_EMPTY_REPLY.to_string())),
+        },
+    };
+    // The reply goes back to the sender, so the two user names swap places
+    let reply = WechatTextReply {
+        to_user_name: parsed_message.from_user_name,
+        from_user_name: parsed_message.to_user_name,
+        create_time: Utc::now().timestamp(),
+        content: reply_content,
+    };
+    Ok(content::Xml(reply.to_xml()))
+}